@@ -54,6 +54,26 @@ pub struct Config {
     pub prefix_key: String,
     /// Color scheme name
     pub color_scheme: String,
+    /// Keep panes open after their process exits
+    pub remain_on_exit: bool,
+    /// Start new panes and tabs in the focused pane's working directory
+    pub inherit_cwd: bool,
+    /// Name tabs and pane borders after the running program
+    pub automatic_rename: bool,
+    /// Seconds kill-pane waits before forcing a process to exit
+    pub kill_timeout: u64,
+    /// Flag background tabs whose panes produce output
+    pub monitor_activity: bool,
+    /// Flag background tabs silent for this many seconds (0 disables)
+    pub monitor_silence: u64,
+    /// Also report activity, silence and bells in the status bar
+    pub alert_messages: bool,
+    /// Which tabs' bells raise alerts: "any", "current", "other" or "none"
+    pub bell_action: String,
+    /// Flash the border of a pane that rings the bell
+    pub visual_bell: bool,
+    /// Pass bells on to the host terminal
+    pub audible_bell: bool,
     /// Tab bar settings
     pub tab_bar: TabBarConfig,
     /// Status bar settings
@@ -69,6 +89,16 @@ impl Default for Config {
             codepage: None,
             prefix_key: "C-b".to_string(),
             color_scheme: "default".to_string(),
+            remain_on_exit: false,
+            inherit_cwd: false,
+            automatic_rename: true,
+            kill_timeout: 3,
+            monitor_activity: false,
+            monitor_silence: 0,
+            alert_messages: false,
+            bell_action: "any".to_string(),
+            visual_bell: false,
+            audible_bell: true,
             tab_bar: TabBarConfig::default(),
             status_bar: StatusBarConfig::default(),
             pane: PaneConfig::default(),
@@ -230,7 +260,7 @@ impl Color {
     }
 
     /// Convert to crossterm Color
-    pub fn to_crossterm(&self) -> crossterm::style::Color {
+    pub fn to_crossterm(self) -> crossterm::style::Color {
         crossterm::style::Color::Rgb {
             r: self.r,
             g: self.g,
@@ -261,8 +261,8 @@ impl CopyMode {
             let start_c = if row == from_row { from_col as usize } else { 0 };
             let end_c = if row == to_row { to_col as usize + 1 } else { line.len() };
             
-            for col in start_c..end_c.min(line.len()) {
-                text.push(line[col].c());
+            for cell in line.iter().take(end_c).skip(start_c) {
+                text.push(cell.c());
             }
             
             if row < to_row {
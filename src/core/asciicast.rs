@@ -0,0 +1,421 @@
+//! asciicast v2 session recording and playback
+//!
+//! Writes a pane's output to asciinema's asciicast v2 format: a JSON header
+//! line followed by one `[time, code, data]` line per event, where `"o"`
+//! events carry output and `"r"` events carry terminal resizes. `Player`
+//! plays such a file back with its original timing.
+//!
+//! See <https://docs.asciinema.org/manual/asciicast/v2/>.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use super::encoding::{Decoder, Encoding};
+
+/// Slowest and fastest playback speed
+const MIN_SPEED: f64 = 0.25;
+const MAX_SPEED: f64 = 16.0;
+
+/// First line of an asciicast v2 file
+#[derive(Debug, Serialize, Deserialize)]
+struct Header {
+    version: u32,
+    width: u16,
+    height: u16,
+    #[serde(default)]
+    timestamp: u64,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    /// Longest pause kept on playback, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    idle_time_limit: Option<f64>,
+}
+
+/// File name for a new recording, unique per second
+pub fn default_file_name() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    format!("wtmux-{}.cast", secs)
+}
+
+/// Records a pane's output and resizes to an asciicast file
+pub struct Recorder {
+    file: File,
+    path: PathBuf,
+    /// When the recording started; event times are relative to it
+    start: Instant,
+    /// asciicast output is UTF-8, so output in other encodings is converted
+    decoder: Decoder,
+}
+
+impl Recorder {
+    /// Create the file and write the header for a terminal of the given size
+    pub fn create(
+        path: impl Into<PathBuf>,
+        cols: u16,
+        rows: u16,
+        encoding: Encoding,
+        title: Option<String>,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        let mut env = BTreeMap::new();
+        env.insert("TERM".to_string(), "xterm-256color".to_string());
+        if let Ok(shell) = std::env::var("SHELL") {
+            env.insert("SHELL".to_string(), shell);
+        }
+        let header = Header {
+            version: 2,
+            width: cols,
+            height: rows,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            env,
+            title,
+            idle_time_limit: None,
+        };
+
+        let mut recorder = Self {
+            file: File::create(&path)?,
+            path,
+            start: Instant::now(),
+            decoder: encoding.decoder(),
+        };
+        recorder.write_line(&header)?;
+        Ok(recorder)
+    }
+
+    /// File being recorded to
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Follow a change of the pane's encoding
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.decoder = encoding.decoder();
+    }
+
+    /// Record a chunk of PTY output
+    pub fn output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        let text = self.decoder.decode(bytes);
+        if text.is_empty() {
+            // Only part of a character so far
+            return Ok(());
+        }
+        self.event("o", &text)
+    }
+
+    /// Record a terminal resize
+    pub fn resize(&mut self, cols: u16, rows: u16) -> io::Result<()> {
+        self.event("r", &format!("{}x{}", cols, rows))
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        // Microsecond precision, like asciinema itself
+        let time = self.start.elapsed().as_micros() as f64 / 1_000_000.0;
+        self.write_line(&(time, code, data))
+    }
+
+    /// Write one JSON line in a single write, so a crash leaves whole lines
+    fn write_line(&mut self, value: &impl Serialize) -> io::Result<()> {
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+}
+
+/// What happens at a point in a recording
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventKind {
+    /// Output written to the terminal
+    Output(String),
+    /// The terminal was resized to (cols, rows)
+    Resize(u16, u16),
+}
+
+/// An event and its time in seconds from the start of the recording
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedEvent {
+    pub time: f64,
+    pub kind: EventKind,
+}
+
+/// A loaded asciicast v2 recording
+#[derive(Debug, Clone)]
+pub struct Recording {
+    /// Terminal size at the start
+    pub width: u16,
+    pub height: u16,
+    pub title: Option<String>,
+    /// Output and resize events in time order; input and marker events
+    /// are left out
+    pub events: Vec<TimedEvent>,
+}
+
+impl Recording {
+    /// Read a recording from a file
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parse the contents of an asciicast v2 file
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let invalid = |line: usize, msg: String| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", line, msg))
+        };
+        let mut lines = content.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+        let (_, first) = lines.next().ok_or_else(|| invalid(1, "empty file".to_string()))?;
+        let header: Header = serde_json::from_str(first).map_err(|e| invalid(1, e.to_string()))?;
+        if header.version != 2 {
+            return Err(invalid(1, format!("unsupported asciicast version {}", header.version)));
+        }
+
+        let mut events = Vec::new();
+        // Pauses longer than the idle time limit are shortened to it
+        let mut last_time = 0.0;
+        let mut cut = 0.0;
+        for (index, line) in lines {
+            let (time, code, data): (f64, String, String) =
+                serde_json::from_str(line).map_err(|e| invalid(index + 1, e.to_string()))?;
+            let kind = match code.as_str() {
+                "o" => EventKind::Output(data),
+                "r" => {
+                    let size = data
+                        .split_once('x')
+                        .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)));
+                    let (cols, rows) = size.ok_or_else(|| invalid(index + 1, format!("bad size {:?}", data)))?;
+                    EventKind::Resize(cols, rows)
+                }
+                _ => continue,
+            };
+            if let Some(limit) = header.idle_time_limit {
+                cut += (time - last_time - limit).max(0.0);
+            }
+            last_time = time;
+            events.push(TimedEvent { time: time - cut, kind });
+        }
+
+        Ok(Self {
+            width: header.width,
+            height: header.height,
+            title: header.title,
+            events,
+        })
+    }
+
+    /// Time of the last event
+    pub fn duration(&self) -> f64 {
+        self.events.last().map(|event| event.time).unwrap_or(0.0)
+    }
+}
+
+/// Plays a recording back in real time, with pause, speed and seeking
+pub struct Player {
+    recording: Recording,
+    /// Index of the next event to play
+    next: usize,
+    /// Playback time at `since`
+    position: f64,
+    since: Instant,
+    paused: bool,
+    speed: f64,
+}
+
+impl Player {
+    /// Start playing `recording` from the beginning
+    pub fn new(recording: Recording) -> Self {
+        Self {
+            recording,
+            next: 0,
+            position: 0.0,
+            since: Instant::now(),
+            paused: false,
+            speed: 1.0,
+        }
+    }
+
+    /// Terminal size at the start of the recording
+    pub fn size(&self) -> (u16, u16) {
+        (self.recording.width, self.recording.height)
+    }
+
+    /// Current playback time in seconds
+    pub fn time(&self) -> f64 {
+        let time = if self.paused {
+            self.position
+        } else {
+            self.position + self.since.elapsed().as_secs_f64() * self.speed
+        };
+        time.min(self.recording.duration())
+    }
+
+    /// Whether every event has been played
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.recording.events.len()
+    }
+
+    /// Take the events that are due by now
+    pub fn take_due(&mut self) -> &[TimedEvent] {
+        let time = self.time();
+        let start = self.next;
+        while self.recording.events.get(self.next).is_some_and(|event| event.time <= time) {
+            self.next += 1;
+        }
+        &self.recording.events[start..self.next]
+    }
+
+    /// How long until the next event is due, if playing
+    pub fn next_due_in(&self) -> Option<Duration> {
+        if self.paused {
+            return None;
+        }
+        let event = self.recording.events.get(self.next)?;
+        let wait = ((event.time - self.time()) / self.speed).max(0.0);
+        Some(Duration::from_secs_f64(wait))
+    }
+
+    /// Pause or resume playback
+    pub fn toggle_pause(&mut self) {
+        self.position = self.time();
+        self.since = Instant::now();
+        self.paused = !self.paused;
+    }
+
+    /// Multiply the playback speed by `factor`, within 1/4x to 16x
+    pub fn change_speed(&mut self, factor: f64) {
+        self.position = self.time();
+        self.since = Instant::now();
+        self.speed = (self.speed * factor).clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    /// Jump to `time` seconds into the recording.
+    ///
+    /// Returns true when jumping back: the terminal must then be reset, as
+    /// the events are played again from the start.
+    pub fn seek(&mut self, time: f64) -> bool {
+        let time = time.clamp(0.0, self.recording.duration());
+        self.position = time;
+        self.since = Instant::now();
+        let rewind = self.next > 0 && self.recording.events[self.next - 1].time > time;
+        if rewind {
+            self.next = 0;
+        }
+        rewind
+    }
+
+    /// Short description of the playback state, e.g. `paused 3.2/10.0s x2`
+    pub fn status(&self) -> String {
+        let state = if self.is_finished() {
+            "ended"
+        } else if self.paused {
+            "paused"
+        } else {
+            "playing"
+        };
+        format!("{} {:.1}/{:.1}s x{}", state, self.time(), self.recording.duration(), self.speed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    #[test]
+    fn test_recording() {
+        let path = std::env::temp_dir().join(format!("wtmux-rec-{}.cast", std::process::id()));
+        let mut recorder = Recorder::create(&path, 80, 24, Encoding::Utf8, Some("demo".to_string())).unwrap();
+
+        let bytes = "\x1b[1m日本\x1b[0m".as_bytes();
+        recorder.output(&bytes[..8]).unwrap();
+        recorder.output(&bytes[8..]).unwrap();
+        recorder.resize(100, 30).unwrap();
+        drop(recorder);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = content
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[0]["height"], 24);
+        assert_eq!(lines[0]["title"], "demo");
+
+        // The character split across reads is recorded whole
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "\x1b[1m日");
+        assert_eq!(lines[2][2], "本\x1b[0m");
+        assert_eq!(lines[3][1], "r");
+        assert_eq!(lines[3][2], "100x30");
+        assert!(lines[3][0].as_f64().unwrap() >= lines[1][0].as_f64().unwrap());
+    }
+
+    #[test]
+    fn test_parse_recording() {
+        let content = concat!(
+            "{\"version\": 2, \"width\": 20, \"height\": 5, \"idle_time_limit\": 2.0}\n",
+            "[0.5, \"o\", \"a\"]\n",
+            "[1.0, \"i\", \"typed\"]\n",
+            "[10.5, \"r\", \"30x6\"]\n",
+            "\n",
+            "[11.0, \"o\", \"b\"]\n",
+        );
+        let recording = Recording::parse(content).unwrap();
+
+        assert_eq!((recording.width, recording.height), (20, 5));
+        let events: Vec<(f64, EventKind)> = recording
+            .events
+            .iter()
+            .map(|event| (event.time, event.kind.clone()))
+            .collect();
+        // The 10 second pause is cut to the 2 second idle time limit
+        assert_eq!(
+            events,
+            vec![
+                (0.5, EventKind::Output("a".to_string())),
+                (2.5, EventKind::Resize(30, 6)),
+                (3.0, EventKind::Output("b".to_string())),
+            ]
+        );
+
+        assert!(Recording::parse("{\"version\": 1, \"width\": 1, \"height\": 1}").is_err());
+        assert!(Recording::parse("{\"version\": 2, \"width\": 1, \"height\": 1}\n[0, \"o\"]").is_err());
+    }
+
+    #[test]
+    fn test_player_seek() {
+        let content = "{\"version\": 2, \"width\": 20, \"height\": 5}\n[1, \"o\", \"a\"]\n[2, \"o\", \"b\"]\n[3, \"o\", \"c\"]\n";
+        let mut player = Player::new(Recording::parse(content).unwrap());
+        player.toggle_pause();
+        assert!(player.take_due().is_empty());
+        assert_eq!(player.next_due_in(), None);
+
+        // Jumping ahead plays everything up to there
+        assert!(!player.seek(2.5));
+        assert_eq!(player.take_due().len(), 2);
+        assert_eq!(player.status(), "paused 2.5/3.0s x1");
+
+        // Jumping back starts over
+        assert!(player.seek(1.5));
+        assert_eq!(player.take_due().len(), 1);
+
+        player.change_speed(2.0);
+        player.toggle_pause();
+        assert!(player.next_due_in().unwrap() <= Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,398 @@
+//! Character encodings of PTY streams
+//!
+//! Each session decodes the child's output and encodes keyboard/paste input
+//! with its own `Encoding`. PTY reads arrive in arbitrary chunks, so a
+//! multi-byte character can be split across two reads; the decoders here
+//! keep the incomplete tail of a chunk and finish it when the next chunk
+//! arrives.
+
+use std::borrow::Cow;
+
+use encoding_rs::{EncoderResult, EUC_JP, SHIFT_JIS};
+
+/// Encoding of the bytes exchanged with a PTY
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// Shift-JIS with Microsoft extensions (Windows codepage 932)
+    Cp932,
+    EucJp,
+    /// ISO-8859-1
+    Latin1,
+}
+
+impl Encoding {
+    /// All supported encodings, in the order the prefix command cycles through them
+    pub const ALL: [Encoding; 4] = [Encoding::Utf8, Encoding::Cp932, Encoding::EucJp, Encoding::Latin1];
+
+    /// Display name (status bar, window title)
+    pub fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Cp932 => "Shift-JIS",
+            Encoding::EucJp => "EUC-JP",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+
+    /// Map a Windows codepage number to an encoding
+    pub fn from_codepage(codepage: u32) -> Option<Self> {
+        match codepage {
+            65001 => Some(Encoding::Utf8),
+            932 => Some(Encoding::Cp932),
+            20932 | 51932 => Some(Encoding::EucJp),
+            28591 => Some(Encoding::Latin1),
+            _ => None,
+        }
+    }
+
+    /// Encoding of the output of a PTY whose child runs with `codepage`
+    ///
+    /// ConPTY converts console output to UTF-8 whatever the codepage, so the
+    /// codepage only changes the stream encoding on other backends.
+    pub fn for_pty_codepage(codepage: Option<u32>) -> Self {
+        if cfg!(windows) {
+            return Encoding::Utf8;
+        }
+        codepage.and_then(Self::from_codepage).unwrap_or_default()
+    }
+
+    /// The encoding after this one in `ALL`, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&e| e == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Create a streaming decoder for output in this encoding
+    pub fn decoder(self) -> Decoder {
+        match self {
+            Encoding::Utf8 => Decoder::Utf8(Utf8Decoder::new()),
+            Encoding::Cp932 => Decoder::Legacy(Box::new(SHIFT_JIS.new_decoder_without_bom_handling())),
+            Encoding::EucJp => Decoder::Legacy(Box::new(EUC_JP.new_decoder_without_bom_handling())),
+            Encoding::Latin1 => Decoder::Latin1,
+        }
+    }
+
+    /// Convert UTF-8 input (keys, paste) to this encoding
+    ///
+    /// Characters the encoding cannot represent become `?`. Bytes that are
+    /// not valid UTF-8 (raw control input) are passed through unchanged.
+    pub fn encode<'a>(self, input: &'a [u8]) -> Cow<'a, [u8]> {
+        if self == Encoding::Utf8 || input.is_ascii() {
+            return Cow::Borrowed(input);
+        }
+
+        let mut out = Vec::with_capacity(input.len());
+        let mut rest = input;
+        while !rest.is_empty() {
+            let (text, raw) = match std::str::from_utf8(rest) {
+                Ok(text) => (text, &[][..]),
+                Err(e) => {
+                    // Valid prefix, then the invalid bytes themselves
+                    let valid = e.valid_up_to();
+                    let invalid = e.error_len().unwrap_or(rest.len() - valid);
+                    let text = std::str::from_utf8(&rest[..valid]).unwrap_or_default();
+                    (text, &rest[valid..valid + invalid])
+                }
+            };
+            self.encode_str(text, &mut out);
+            out.extend_from_slice(raw);
+            rest = &rest[text.len() + raw.len()..];
+        }
+        Cow::Owned(out)
+    }
+
+    /// Append `text` in this encoding to `out`
+    fn encode_str(self, text: &str, out: &mut Vec<u8>) {
+        let encoding = match self {
+            Encoding::Utf8 => {
+                out.extend_from_slice(text.as_bytes());
+                return;
+            }
+            Encoding::Latin1 => {
+                out.extend(text.chars().map(|c| u8::try_from(c).unwrap_or(b'?')));
+                return;
+            }
+            Encoding::Cp932 => SHIFT_JIS,
+            Encoding::EucJp => EUC_JP,
+        };
+
+        let mut encoder = encoding.new_encoder();
+        let mut rest = text;
+        loop {
+            if let Some(needed) = encoder.max_buffer_length_from_utf8_without_replacement(rest.len()) {
+                out.reserve(needed);
+            }
+            let (result, read) = encoder.encode_from_utf8_to_vec_without_replacement(rest, out, true);
+            rest = &rest[read..];
+            match result {
+                EncoderResult::InputEmpty => break,
+                EncoderResult::OutputFull => {}
+                EncoderResult::Unmappable(_) => out.push(b'?'),
+            }
+        }
+    }
+}
+
+/// Streaming decoder for one of the supported encodings
+pub enum Decoder {
+    Utf8(Utf8Decoder),
+    Latin1,
+    Legacy(Box<encoding_rs::Decoder>),
+}
+
+impl Decoder {
+    /// Decode a chunk, keeping an incomplete trailing sequence for the next call
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        match self {
+            Decoder::Utf8(decoder) => decoder.decode(bytes),
+            Decoder::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+            Decoder::Legacy(decoder) => {
+                let mut out = String::new();
+                let mut rest = bytes;
+                loop {
+                    if let Some(needed) = decoder.max_utf8_buffer_length(rest.len()) {
+                        out.reserve(needed);
+                    }
+                    let (result, read, _) = decoder.decode_to_string(rest, &mut out, false);
+                    rest = &rest[read..];
+                    if result == encoding_rs::CoderResult::InputEmpty {
+                        break;
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Streaming UTF-8 decoder
+///
+/// Invalid input is replaced with U+FFFD using the same "maximal subpart"
+/// rule as `String::from_utf8_lossy`, so the result does not depend on how
+/// the input was split into chunks.
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    /// Code point bits collected so far
+    code_point: u32,
+    /// Continuation bytes still needed for the current sequence
+    needed: u8,
+    /// Bytes of the current sequence seen so far
+    seen: u8,
+    /// Valid range for the next continuation byte
+    lower: u8,
+    upper: u8,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self {
+            lower: 0x80,
+            upper: 0xBF,
+            ..Self::default()
+        }
+    }
+
+    /// Whether an incomplete sequence is waiting for more bytes
+    #[allow(dead_code)]
+    pub fn is_pending(&self) -> bool {
+        self.needed > 0
+    }
+
+    /// Decode a chunk, keeping an incomplete trailing sequence for the next call
+    pub fn decode(&mut self, bytes: &[u8]) -> String {
+        let mut out = String::with_capacity(bytes.len());
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let byte = bytes[i];
+
+            if self.needed == 0 {
+                match byte {
+                    0x00..=0x7F => out.push(byte as char),
+                    0xC2..=0xDF => self.begin(1, byte & 0x1F, 0x80, 0xBF),
+                    0xE0 => self.begin(2, byte & 0x0F, 0xA0, 0xBF),
+                    0xED => self.begin(2, byte & 0x0F, 0x80, 0x9F),
+                    0xE1..=0xEF => self.begin(2, byte & 0x0F, 0x80, 0xBF),
+                    0xF0 => self.begin(3, byte & 0x07, 0x90, 0xBF),
+                    0xF4 => self.begin(3, byte & 0x07, 0x80, 0x8F),
+                    0xF1..=0xF3 => self.begin(3, byte & 0x07, 0x80, 0xBF),
+                    // Stray continuation byte or a byte that never appears in UTF-8
+                    _ => out.push(char::REPLACEMENT_CHARACTER),
+                }
+                i += 1;
+                continue;
+            }
+
+            if byte < self.lower || byte > self.upper {
+                // Sequence cut short: replace what we have and reprocess this byte
+                self.reset();
+                out.push(char::REPLACEMENT_CHARACTER);
+                continue;
+            }
+
+            self.code_point = (self.code_point << 6) | (byte & 0x3F) as u32;
+            self.seen += 1;
+            self.lower = 0x80;
+            self.upper = 0xBF;
+            i += 1;
+
+            if self.seen == self.needed {
+                // The range checks above rule out surrogates and overlongs
+                out.push(char::from_u32(self.code_point).unwrap_or(char::REPLACEMENT_CHARACTER));
+                self.reset();
+            }
+        }
+
+        out
+    }
+
+    /// Start a multi-byte sequence
+    fn begin(&mut self, needed: u8, bits: u8, lower: u8, upper: u8) {
+        self.code_point = bits as u32;
+        self.needed = needed;
+        self.seen = 0;
+        self.lower = lower;
+        self.upper = upper;
+    }
+
+    /// Forget the current sequence
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode `chunks` one after another with a single decoder
+    fn decode_chunks(chunks: &[&[u8]]) -> String {
+        let mut decoder = Utf8Decoder::new();
+        chunks.iter().map(|chunk| decoder.decode(chunk)).collect()
+    }
+
+    #[test]
+    fn test_decode_whole() {
+        let text = "ls -la\r\n日本語のファイル名.txt ✓ 🎉\x1b[0m";
+        assert_eq!(decode_chunks(&[text.as_bytes()]), text);
+    }
+
+    #[test]
+    fn test_every_split_point() {
+        let text = "aé日本語🎉\x1b[31mテスト\x1b[0m";
+        let bytes = text.as_bytes();
+        for split in 0..=bytes.len() {
+            let (a, b) = bytes.split_at(split);
+            assert_eq!(decode_chunks(&[a, b]), text, "split at {}", split);
+        }
+    }
+
+    #[test]
+    fn test_every_two_split_points() {
+        let text = "日🎉本";
+        let bytes = text.as_bytes();
+        for first in 0..=bytes.len() {
+            for second in first..=bytes.len() {
+                let chunks = [&bytes[..first], &bytes[first..second], &bytes[second..]];
+                assert_eq!(decode_chunks(&chunks), text, "split at {} and {}", first, second);
+            }
+        }
+    }
+
+    #[test]
+    fn test_byte_at_a_time() {
+        let text = "プロンプト> échec 🎉";
+        let chunks: Vec<&[u8]> = text.as_bytes().chunks(1).collect();
+        assert_eq!(decode_chunks(&chunks), text);
+    }
+
+    #[test]
+    fn test_invalid_input_matches_lossy() {
+        let samples: &[&[u8]] = &[
+            b"\xff",
+            b"a\x80b",
+            b"\xc0\x80",
+            b"\xe3\x81A",
+            b"\xed\xa0\x80",
+            b"\xf4\x90\x80\x80",
+            b"\xf0\x9f\x8e",
+            b"\xe6\x97\xa5\xe6\x9c",
+            b"x\xf8\xe6\x97\xa5\xc3",
+        ];
+        for sample in samples {
+            let expected = String::from_utf8_lossy(sample);
+            for split in 0..=sample.len() {
+                let (a, b) = sample.split_at(split);
+                let mut decoder = Utf8Decoder::new();
+                let mut decoded = decoder.decode(a) + &decoder.decode(b);
+                if decoder.is_pending() {
+                    // Input ended inside a sequence
+                    decoded.push(char::REPLACEMENT_CHARACTER);
+                }
+                assert_eq!(decoded, expected, "{:x?} split at {}", sample, split);
+            }
+        }
+    }
+
+    #[test]
+    fn test_legacy_every_split_point() {
+        let text = "ファイル一覧 ok ①";
+        for encoding in [Encoding::Cp932, Encoding::EucJp] {
+            let bytes = encoding.encode(text.as_bytes());
+            for split in 0..=bytes.len() {
+                let (a, b) = bytes.split_at(split);
+                let mut decoder = encoding.decoder();
+                let decoded = decoder.decode(a) + &decoder.decode(b);
+                assert_eq!(decoded, text, "{:?} split at {}", encoding, split);
+            }
+        }
+    }
+
+    #[test]
+    fn test_cp932_bytes() {
+        // "日本" in Shift-JIS
+        let sjis = b"\x93\xfa\x96\x7b";
+        assert_eq!(Encoding::Cp932.decoder().decode(sjis), "日本");
+        assert_eq!(Encoding::Cp932.encode("日本".as_bytes()).as_ref(), sjis);
+    }
+
+    #[test]
+    fn test_latin1_round_trip() {
+        assert_eq!(Encoding::Latin1.decoder().decode(b"caf\xe9"), "café");
+        assert_eq!(Encoding::Latin1.encode("café 日".as_bytes()).as_ref(), b"caf\xe9 ?");
+    }
+
+    #[test]
+    fn test_encode_passes_control_input_through() {
+        // Escape sequences are ASCII and unchanged in every encoding
+        for encoding in Encoding::ALL {
+            assert_eq!(encoding.encode(b"\x1b[A\r").as_ref(), b"\x1b[A\r");
+        }
+        // Unmappable characters become '?'
+        assert_eq!(Encoding::EucJp.encode("a🎉b".as_bytes()).as_ref(), b"a?b");
+        // Invalid UTF-8 is passed through
+        assert_eq!(Encoding::Cp932.encode(b"\xff\x1b").as_ref(), b"\xff\x1b");
+    }
+
+    #[test]
+    fn test_encoding_cycle_and_codepage() {
+        let mut encoding = Encoding::Utf8;
+        for expected in [Encoding::Cp932, Encoding::EucJp, Encoding::Latin1, Encoding::Utf8] {
+            encoding = encoding.next();
+            assert_eq!(encoding, expected);
+        }
+        assert_eq!(Encoding::from_codepage(932), Some(Encoding::Cp932));
+        assert_eq!(Encoding::from_codepage(437), None);
+    }
+
+    #[test]
+    fn test_pending_sequence_waits_for_next_chunk() {
+        let mut decoder = Utf8Decoder::new();
+        assert_eq!(decoder.decode(b"ok \xe6\x97"), "ok ");
+        assert!(decoder.is_pending());
+        assert_eq!(decoder.decode(b"\xa5"), "日");
+        assert!(!decoder.is_pending());
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! This module contains the low-level terminal emulation logic:
 //!
-//! - **pty**: Windows ConPTY wrapper for pseudo-terminal operations
+//! - **asciicast**: Recording pane output in asciicast v2 format
+//! - **encoding**: Streaming decoder for PTY output
+//! - **pipe**: Copying pane output to a file or command (pipe-pane)
+//! - **pty**: `Pty` trait with ConPTY (Windows) and openpty (Unix) backends
 //! - **term**: VT100/VT220 terminal state and ANSI escape sequence parser
 //! - **session**: High-level session combining PTY + terminal state
 //!
@@ -10,13 +13,16 @@
 //!
 //! ```text
 //! Session
-//! ├── ConPty (PTY I/O with shell process)
+//! ├── Pty (PTY I/O with shell process: ConPty / UnixPty)
 //! └── TerminalState
 //!     ├── Screen (cell grid + attributes)
 //!     ├── Cursor (position + visibility)
 //!     └── Parser (ANSI escape sequences)
 //! ```
 
+pub mod asciicast;
+pub mod encoding;
+pub mod pipe;
 pub mod pty;
 pub mod term;
 pub mod session;
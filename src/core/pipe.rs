@@ -0,0 +1,262 @@
+//! Pane output piping (tmux `pipe-pane`)
+//!
+//! The session's reader thread hands every chunk it reads from the PTY to an
+//! `OutputPipe` before decoding, so the copy is exactly what the process
+//! wrote. Escape sequences can optionally be stripped for a plain text log.
+//!
+//! The file or command is written from a thread of its own, so a target
+//! that stops reading loses output instead of stalling the pane.
+
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+
+/// Output chunks waiting for the writer thread; more are dropped
+const PIPE_CHANNEL_CAPACITY: usize = 256;
+
+/// Where pane output is copied to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PipeTarget {
+    /// Append to a file
+    File(PathBuf),
+    /// Write to the standard input of a shell command
+    Command(String),
+}
+
+impl PipeTarget {
+    /// Parse prompt input: `|command` pipes into a command, anything else
+    /// names a file
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if let Some(command) = input.strip_prefix('|') {
+            let command = command.trim();
+            (!command.is_empty()).then(|| PipeTarget::Command(command.to_string()))
+        } else {
+            (!input.is_empty()).then(|| PipeTarget::File(PathBuf::from(input)))
+        }
+    }
+}
+
+impl fmt::Display for PipeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PipeTarget::File(path) => write!(f, "{}", path.display()),
+            PipeTarget::Command(command) => write!(f, "|{}", command),
+        }
+    }
+}
+
+/// An open destination for a pane's output
+pub struct OutputPipe {
+    target: PipeTarget,
+    /// Chunks for the writer thread
+    tx: SyncSender<Vec<u8>>,
+    /// Set by the writer thread once writing has failed
+    failed: Arc<AtomicBool>,
+}
+
+impl OutputPipe {
+    /// Open the file for appending or start the command
+    pub fn open(target: PipeTarget, strip_escapes: bool) -> io::Result<Self> {
+        let (sink, child): (Box<dyn Write + Send>, _) = match &target {
+            PipeTarget::File(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path)?;
+                (Box::new(file), None)
+            }
+            PipeTarget::Command(command) => {
+                let mut child = shell_command(command)
+                    .stdin(Stdio::piped())
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn()?;
+                let stdin = child.stdin.take().ok_or(io::ErrorKind::BrokenPipe)?;
+                (Box::new(stdin), Some(child))
+            }
+        };
+        let (tx, rx) = mpsc::sync_channel(PIPE_CHANNEL_CAPACITY);
+        let failed = Arc::new(AtomicBool::new(false));
+        let writer_failed = failed.clone();
+        let stripper = strip_escapes.then(EscapeStripper::default);
+        thread::spawn(move || write_chunks(rx, sink, child, stripper, &writer_failed));
+        Ok(Self { target, tx, failed })
+    }
+
+    /// Where the output goes
+    pub fn target(&self) -> &PipeTarget {
+        &self.target
+    }
+
+    /// Copy a chunk of PTY output without waiting for the target
+    ///
+    /// The chunk is dropped if the target has fallen behind. Fails once
+    /// writing to the target has failed.
+    pub fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if self.failed.load(Ordering::SeqCst) {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        match self.tx.try_send(bytes.to_vec()) {
+            Ok(()) | Err(TrySendError::Full(_)) => Ok(()),
+            Err(TrySendError::Disconnected(_)) => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+}
+
+/// Writer thread: copy chunks to the target until the `OutputPipe` is
+/// dropped or writing fails
+fn write_chunks(
+    rx: Receiver<Vec<u8>>,
+    mut sink: Box<dyn Write + Send>,
+    child: Option<Child>,
+    mut stripper: Option<EscapeStripper>,
+    failed: &AtomicBool,
+) {
+    for chunk in rx {
+        let result = match &mut stripper {
+            Some(stripper) => sink.write_all(&stripper.strip(&chunk)),
+            None => sink.write_all(&chunk),
+        };
+        if result.is_err() {
+            failed.store(true, Ordering::SeqCst);
+            break;
+        }
+    }
+    // Closing stdin tells the command its input has ended
+    drop(sink);
+    if let Some(mut child) = child {
+        let _ = child.wait();
+    }
+}
+
+/// Run `command` with the platform shell
+fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let mut cmd = Command::new("cmd.exe");
+        cmd.raw_arg(format!("/d /s /c \"{}\"", command));
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(command);
+        cmd
+    }
+}
+
+/// Where `EscapeStripper` is within an escape sequence
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+enum StripState {
+    #[default]
+    Ground,
+    /// After ESC
+    Escape,
+    /// In a CSI sequence, up to its final byte
+    Csi,
+    /// In an OSC, DCS, SOS, PM or APC string, up to BEL or ST
+    String,
+    /// After ESC inside a string
+    StringEscape,
+}
+
+/// Removes escape sequences and control characters other than newline and
+/// tab from a byte stream, keeping state across chunks
+#[derive(Debug, Default)]
+struct EscapeStripper {
+    state: StripState,
+}
+
+impl EscapeStripper {
+    fn strip(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(bytes.len());
+        for &b in bytes {
+            self.state = match (self.state, b) {
+                (_, 0x18 | 0x1a) => StripState::Ground,
+                (StripState::Ground, 0x1b) => StripState::Escape,
+                (StripState::Ground, b'\n' | b'\t') => {
+                    out.push(b);
+                    StripState::Ground
+                }
+                (StripState::Ground, b) if b < 0x20 || b == 0x7f => StripState::Ground,
+                (StripState::Ground, b) => {
+                    out.push(b);
+                    StripState::Ground
+                }
+                (StripState::Escape, b'[') => StripState::Csi,
+                (StripState::Escape, b']' | b'P' | b'X' | b'^' | b'_') => StripState::String,
+                // Intermediate bytes, as in ESC ( B
+                (StripState::Escape, 0x20..=0x2f) => StripState::Escape,
+                (StripState::Escape, _) => StripState::Ground,
+                (StripState::Csi, 0x40..=0x7e) => StripState::Ground,
+                (StripState::Csi, _) => StripState::Csi,
+                (StripState::String, 0x07) => StripState::Ground,
+                (StripState::String, 0x1b) => StripState::StringEscape,
+                (StripState::String, _) => StripState::String,
+                (StripState::StringEscape, b'\\') => StripState::Ground,
+                (StripState::StringEscape, _) => StripState::String,
+            };
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pty::wait_until;
+
+    #[test]
+    fn test_parse_target() {
+        assert_eq!(PipeTarget::parse(" pane.log "), Some(PipeTarget::File("pane.log".into())));
+        assert_eq!(
+            PipeTarget::parse("| grep ERROR"),
+            Some(PipeTarget::Command("grep ERROR".to_string()))
+        );
+        assert_eq!(PipeTarget::parse("|"), None);
+        assert_eq!(PipeTarget::parse(""), None);
+    }
+
+    #[test]
+    fn test_strip_escapes() {
+        let mut stripper = EscapeStripper::default();
+        let out = stripper.strip(b"\x1b[1;31mred\x1b[0m\r\n\x1b]0;title\x07\x1b(Bok\tdone\x1b]2;x\x1b\\\n");
+        assert_eq!(out, b"red\nok\tdone\n");
+
+        // A sequence split across chunks
+        assert_eq!(stripper.strip(b"a\x1b["), b"a");
+        assert_eq!(stripper.strip(b"32mb"), b"b");
+    }
+
+    #[test]
+    fn test_pipe_to_file() {
+        let path = std::env::temp_dir().join(format!("wtmux-pipe-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut pipe = OutputPipe::open(PipeTarget::File(path.clone()), true).unwrap();
+        pipe.write(b"\x1b[32mhello\x1b[0m\r\n").unwrap();
+        drop(pipe);
+
+        assert!(wait_until(|| std::fs::read_to_string(&path).is_ok_and(|text| text == "hello\n")));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_pipe_to_stalled_command() {
+        // The command never reads, so its stdin fills up
+        let mut pipe = OutputPipe::open(PipeTarget::Command("sleep 5".to_string()), false).unwrap();
+        let chunk = vec![b'x'; 4096];
+        let start = std::time::Instant::now();
+        for _ in 0..1024 {
+            pipe.write(&chunk).unwrap();
+        }
+        drop(pipe);
+        assert!(start.elapsed() < std::time::Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,500 @@
+//! ConPTY wrapper for Windows
+//!
+//! This module provides a safe wrapper around Windows ConPTY (Console Pseudo Terminal)
+//! for creating and managing pseudo-terminal sessions.
+
+use std::ffi::{c_void, OsString};
+use std::io;
+use std::os::windows::ffi::OsStrExt;
+use std::sync::Mutex;
+
+use windows::Win32::Foundation::{CloseHandle, HANDLE};
+use windows::Win32::Security::SECURITY_ATTRIBUTES;
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, ReadFile, WriteFile, FILE_ATTRIBUTE_NORMAL, FILE_GENERIC_READ,
+    FILE_GENERIC_WRITE, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+};
+use windows::Win32::System::Console::{
+    ClosePseudoConsole, CreatePseudoConsole, ResizePseudoConsole, COORD, HPCON,
+};
+use windows::Win32::System::Pipes::CreatePipe;
+use windows::Win32::System::Threading::{
+    CreateProcessW, DeleteProcThreadAttributeList, GetExitCodeProcess,
+    InitializeProcThreadAttributeList, TerminateProcess, UpdateProcThreadAttribute,
+    WaitForSingleObject,
+    CREATE_UNICODE_ENVIRONMENT, EXTENDED_STARTUPINFO_PRESENT, INFINITE,
+    LPPROC_THREAD_ATTRIBUTE_LIST, PROCESS_INFORMATION, STARTF_USESTDHANDLES, STARTUPINFOEXW,
+};
+use windows::core::{PCWSTR, PWSTR};
+
+use super::{Pty, PtyError, Result, Signal, SpawnSpec};
+
+/// Shell used when the spawn spec names no program
+const DEFAULT_SHELL: &str = "cmd.exe";
+
+/// How long to wait for `chcp` to set the console codepage
+const CHCP_TIMEOUT_MS: u32 = 5000;
+
+/// Ctrl+Break key down and up in win32-input-mode (VK_CANCEL, scan code
+/// 0x46, left Ctrl held), which the pseudo console turns into CTRL_BREAK_EVENT
+const CTRL_BREAK_INPUT: &[u8] = b"\x1b[3;70;0;1;8;1_\x1b[3;70;0;0;8;1_";
+
+/// ConPTY handle wrapper
+pub struct ConPty {
+    /// Pseudo console handle; taken out when it is closed
+    hpc: Mutex<Option<HPCON>>,
+    input_write: HANDLE,
+    output_read: HANDLE,
+    process: PROCESS_INFORMATION,
+    #[allow(dead_code)]
+    cols: u16,
+    #[allow(dead_code)]
+    rows: u16,
+}
+
+// Safety: ConPty handles are thread-safe when accessed properly
+unsafe impl Send for ConPty {}
+// ConPty needs to be Send + Sync for Arc
+unsafe impl Sync for ConPty {}
+
+impl ConPty {
+    /// Create a new ConPTY instance and spawn a shell
+    #[allow(dead_code)]
+    pub fn new(cols: u16, rows: u16, command: Option<&str>) -> Result<Self> {
+        let spec = command.map(SpawnSpec::from_command_line).unwrap_or_default();
+        Self::spawn(cols, rows, &spec)
+    }
+
+    /// Quote one argument for a Windows command line (CommandLineToArgvW rules)
+    fn quote_arg(arg: &str) -> String {
+        const SPECIAL: &[char] = &[' ', '\t', '"'];
+        if !arg.is_empty() && !arg.contains(SPECIAL) {
+            return arg.to_string();
+        }
+
+        let mut quoted = String::from('"');
+        let mut backslashes = 0;
+        for ch in arg.chars() {
+            match ch {
+                '\\' => backslashes += 1,
+                '"' => {
+                    // Backslashes before a quote are doubled, and the quote escaped
+                    quoted.extend(std::iter::repeat('\\').take(backslashes * 2 + 1));
+                    quoted.push('"');
+                    backslashes = 0;
+                }
+                _ => {
+                    quoted.extend(std::iter::repeat('\\').take(backslashes));
+                    quoted.push(ch);
+                    backslashes = 0;
+                }
+            }
+        }
+        // Backslashes before the closing quote are doubled
+        quoted.extend(std::iter::repeat('\\').take(backslashes * 2));
+        quoted.push('"');
+        quoted
+    }
+
+    /// Build the command line for CreateProcessW
+    fn command_line(spec: &SpawnSpec) -> String {
+        let program = spec.program.as_deref().unwrap_or(DEFAULT_SHELL);
+        std::iter::once(program)
+            .chain(spec.args.iter().map(String::as_str))
+            .map(Self::quote_arg)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Switch the pseudo console to `codepage` by running `chcp` in it
+    ///
+    /// The codepage belongs to the console, so it stays in effect for the
+    /// program started afterwards. chcp's own output goes to NUL.
+    unsafe fn apply_codepage(
+        attr_list: LPPROC_THREAD_ATTRIBUTE_LIST,
+        codepage: u32,
+    ) -> Result<()> {
+        // Inheritable, so chcp can use it for its standard handles
+        let security = SECURITY_ATTRIBUTES {
+            nLength: std::mem::size_of::<SECURITY_ATTRIBUTES>() as u32,
+            lpSecurityDescriptor: std::ptr::null_mut(),
+            bInheritHandle: true.into(),
+        };
+        let nul_wide: Vec<u16> = "NUL".encode_utf16().chain(std::iter::once(0)).collect();
+        let nul = CreateFileW(
+            PCWSTR(nul_wide.as_ptr()),
+            (FILE_GENERIC_READ | FILE_GENERIC_WRITE).0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            Some(&security),
+            OPEN_EXISTING,
+            FILE_ATTRIBUTE_NORMAL,
+            HANDLE::default(),
+        )
+        .map_err(PtyError::ProcessSpawn)?;
+
+        let mut startup_info = STARTUPINFOEXW {
+            StartupInfo: std::mem::zeroed(),
+            lpAttributeList: attr_list,
+        };
+        startup_info.StartupInfo.cb = std::mem::size_of::<STARTUPINFOEXW>() as u32;
+        startup_info.StartupInfo.dwFlags = STARTF_USESTDHANDLES;
+        startup_info.StartupInfo.hStdInput = nul;
+        startup_info.StartupInfo.hStdOutput = nul;
+        startup_info.StartupInfo.hStdError = nul;
+
+        let mut cmd_wide: Vec<u16> = format!("chcp.com {}", codepage)
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        let mut process_info = PROCESS_INFORMATION::default();
+        let created = CreateProcessW(
+            PCWSTR::null(),
+            PWSTR(cmd_wide.as_mut_ptr()),
+            None,
+            None,
+            true,
+            EXTENDED_STARTUPINFO_PRESENT,
+            None,
+            PCWSTR::null(),
+            &startup_info.StartupInfo,
+            &mut process_info,
+        );
+        let _ = CloseHandle(nul);
+        created.map_err(PtyError::ProcessSpawn)?;
+
+        WaitForSingleObject(process_info.hProcess, CHCP_TIMEOUT_MS);
+        let _ = CloseHandle(process_info.hProcess);
+        let _ = CloseHandle(process_info.hThread);
+        Ok(())
+    }
+
+    /// Build a Unicode environment block: our environment plus the spec's
+    ///
+    /// Inherited variables need not be valid Unicode, so they are passed
+    /// through as UTF-16 without decoding.
+    fn environment_block(spec: &SpawnSpec) -> Vec<u16> {
+        let mut vars: Vec<(OsString, OsString)> = std::env::vars_os()
+            .filter(|(key, _)| !spec.env.iter().any(|(k, _)| key.eq_ignore_ascii_case(k)))
+            .collect();
+        vars.extend(spec.env.iter().map(|(key, value)| (key.into(), value.into())));
+        // Windows expects the block sorted by name, ignoring case
+        vars.sort_by_key(|(key, _)| key.to_string_lossy().to_uppercase());
+
+        let mut block = Vec::new();
+        for (key, value) in vars {
+            block.extend(key.encode_wide());
+            block.push(b'=' as u16);
+            block.extend(value.encode_wide());
+            block.push(0);
+        }
+        block.push(0);
+        block
+    }
+
+    unsafe fn create_internal(cols: u16, rows: u16, spec: &SpawnSpec) -> Result<Self> {
+        // Create pipes for PTY communication
+        let mut pty_input_read = HANDLE::default();
+        let mut pty_input_write = HANDLE::default();
+        let mut pty_output_read = HANDLE::default();
+        let mut pty_output_write = HANDLE::default();
+
+        // Input pipe (we write, PTY reads)
+        CreatePipe(&mut pty_input_read, &mut pty_input_write, None, 0)
+            .map_err(PtyError::PipeCreation)?;
+
+        // Output pipe (PTY writes, we read)
+        CreatePipe(&mut pty_output_read, &mut pty_output_write, None, 0)
+            .map_err(PtyError::PipeCreation)?;
+
+        // Create pseudo console
+        let size = COORD {
+            X: cols as i16,
+            Y: rows as i16,
+        };
+
+        let hpc = CreatePseudoConsole(size, pty_input_read, pty_output_write, 0)
+            .map_err(PtyError::PtyCreation)?;
+
+        // Close the handles that the ConPTY now owns
+        let _ = CloseHandle(pty_input_read);
+        let _ = CloseHandle(pty_output_write);
+
+        // Prepare process startup
+        let mut attr_list_size: usize = 0;
+        let _ = InitializeProcThreadAttributeList(
+            LPPROC_THREAD_ATTRIBUTE_LIST::default(),
+            1,
+            0,
+            &mut attr_list_size,
+        );
+
+        let mut attr_list_buffer = vec![0u8; attr_list_size];
+        let attr_list = LPPROC_THREAD_ATTRIBUTE_LIST(attr_list_buffer.as_mut_ptr() as *mut _);
+
+        InitializeProcThreadAttributeList(attr_list, 1, 0, &mut attr_list_size)
+            .map_err(PtyError::ProcessSpawn)?;
+
+        // Associate ConPTY with the process
+        const PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE: usize = 0x00020016;
+        UpdateProcThreadAttribute(
+            attr_list,
+            0,
+            PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE,
+            Some(hpc.0 as *const _),
+            std::mem::size_of::<HPCON>(),
+            None,
+            None,
+        )
+        .map_err(PtyError::ProcessSpawn)?;
+
+        if let Some(codepage) = spec.codepage {
+            if let Err(e) = Self::apply_codepage(attr_list, codepage) {
+                tracing::warn!("Failed to set codepage {}: {}", codepage, e);
+            }
+        }
+
+        // Prepare startup info
+        let mut startup_info = STARTUPINFOEXW {
+            StartupInfo: std::mem::zeroed(),
+            lpAttributeList: attr_list,
+        };
+        startup_info.StartupInfo.cb = std::mem::size_of::<STARTUPINFOEXW>() as u32;
+
+        let mut process_info = PROCESS_INFORMATION::default();
+
+        // Command, environment and working directory from the spec
+        let mut cmd_wide: Vec<u16> = Self::command_line(spec)
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect();
+        let environment = Self::environment_block(spec);
+        let cwd_wide: Option<Vec<u16>> = spec.cwd.as_ref().map(|cwd| {
+            cwd.as_os_str()
+                .to_string_lossy()
+                .encode_utf16()
+                .chain(std::iter::once(0))
+                .collect()
+        });
+        let cwd = cwd_wide
+            .as_ref()
+            .map(|wide| PCWSTR(wide.as_ptr()))
+            .unwrap_or(PCWSTR::null());
+
+        // Create process
+        CreateProcessW(
+            PCWSTR::null(),
+            PWSTR(cmd_wide.as_mut_ptr()),
+            None,
+            None,
+            false,
+            EXTENDED_STARTUPINFO_PRESENT | CREATE_UNICODE_ENVIRONMENT,
+            Some(environment.as_ptr() as *const c_void),
+            cwd,
+            &startup_info.StartupInfo,
+            &mut process_info,
+        )
+        .map_err(PtyError::ProcessSpawn)?;
+
+        DeleteProcThreadAttributeList(attr_list);
+
+        Ok(ConPty {
+            hpc: Mutex::new(Some(hpc)),
+            input_write: pty_input_write,
+            output_read: pty_output_read,
+            process: process_info,
+            cols,
+            rows,
+        })
+    }
+
+    /// Get current size
+    #[allow(dead_code)]
+    pub fn size(&self) -> (u16, u16) {
+        (self.cols, self.rows)
+    }
+
+    /// Get the output read handle (for cancellation)
+    #[allow(dead_code)]
+    pub fn output_handle(&self) -> HANDLE {
+        self.output_read
+    }
+
+    /// Close the pseudo console if that hasn't happened yet
+    ///
+    /// The handle is taken under the lock, so a resize never uses it once
+    /// closing has begun, but closed outside it: closing can block until the
+    /// remaining output has been read.
+    fn close_pseudo_console(&self) {
+        let hpc = self.hpc.lock().ok().and_then(|mut hpc| hpc.take());
+        if let Some(hpc) = hpc {
+            unsafe {
+                ClosePseudoConsole(hpc);
+            }
+        }
+    }
+}
+
+impl Pty for ConPty {
+    fn spawn(cols: u16, rows: u16, spec: &SpawnSpec) -> Result<Self> {
+        unsafe { Self::create_internal(cols, rows, spec) }
+    }
+
+    /// Read bytes from the PTY (output from shell), blocking until available
+    fn read(&self, buffer: &mut [u8]) -> Result<usize> {
+        let mut read: u32 = 0;
+
+        unsafe {
+            // Fails with ERROR_BROKEN_PIPE once the pseudo console is closed
+            // and the remaining output has been read
+            ReadFile(self.output_read, Some(buffer), Some(&mut read), None)
+                .map_err(|e| PtyError::Read(io::Error::from_raw_os_error(e.code().0 as i32)))?;
+        }
+
+        if read == 0 {
+            return Err(PtyError::Read(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Pipe closed",
+            )));
+        }
+
+        Ok(read as usize)
+    }
+
+    /// Write bytes to the PTY (input to shell)
+    fn write(&self, data: &[u8]) -> Result<usize> {
+        let mut written: u32 = 0;
+
+        unsafe {
+            WriteFile(self.input_write, Some(data), Some(&mut written), None)
+                .map_err(|e| PtyError::Write(io::Error::from_raw_os_error(e.code().0 as i32)))?;
+        }
+
+        Ok(written as usize)
+    }
+
+    /// Resize the pseudo console
+    fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        let size = COORD {
+            X: cols as i16,
+            Y: rows as i16,
+        };
+
+        // The handle is released once the process has exited; a dead pane
+        // can still be resized by layout changes. Holding the lock keeps it
+        // from being closed during the call.
+        let Ok(hpc) = self.hpc.lock() else {
+            return Ok(());
+        };
+        if let Some(hpc) = *hpc {
+            unsafe {
+                ResizePseudoConsole(hpc, size).map_err(PtyError::Resize)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        unsafe {
+            let result = WaitForSingleObject(self.process.hProcess, 0);
+            result.0 != 0 // WAIT_OBJECT_0 = 0 means signaled (exited)
+        }
+    }
+
+    fn exit_code(&self) -> Option<u32> {
+        if self.is_running() {
+            return None;
+        }
+
+        let mut exit_code: u32 = 0;
+        unsafe {
+            if GetExitCodeProcess(self.process.hProcess, &mut exit_code).is_ok() {
+                Some(exit_code)
+            } else {
+                None
+            }
+        }
+    }
+
+    fn wait(&self) {
+        unsafe {
+            WaitForSingleObject(self.process.hProcess, INFINITE);
+        }
+    }
+
+    fn signal(&self, signal: Signal) -> Result<()> {
+        if let Some(code) = self.exit_code() {
+            return Err(PtyError::ProcessExited(code));
+        }
+
+        // Console control events can only be raised from inside the pseudo
+        // console, so they are sent as input
+        match signal {
+            Signal::Interrupt => self.write(b"\x03").map(|_| ()),
+            Signal::Terminate => self.write(CTRL_BREAK_INPUT).map(|_| ()),
+            Signal::Kill => {
+                unsafe {
+                    TerminateProcess(self.process.hProcess, 1).map_err(PtyError::Signal)?;
+                }
+                // End whatever else is still attached to the console
+                self.close_pseudo_console();
+                Ok(())
+            }
+        }
+    }
+
+    fn close(&self) {
+        // ConPTY keeps the output pipe open after the process exits; closing
+        // the pseudo console ends it (and any processes still attached)
+        self.close_pseudo_console();
+    }
+}
+
+impl Drop for ConPty {
+    fn drop(&mut self) {
+        unsafe {
+            // Close the pseudo console first
+            self.close_pseudo_console();
+
+            // Close handles
+            let _ = CloseHandle(self.input_write);
+            let _ = CloseHandle(self.output_read);
+            let _ = CloseHandle(self.process.hProcess);
+            let _ = CloseHandle(self.process.hThread);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_arg() {
+        assert_eq!(ConPty::quote_arg("pwsh.exe"), "pwsh.exe");
+        assert_eq!(ConPty::quote_arg(r"C:\Program Files\x.exe"), r#""C:\Program Files\x.exe""#);
+        assert_eq!(ConPty::quote_arg(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(ConPty::quote_arg(r"dir\ x\"), r#""dir\ x\\""#);
+        assert_eq!(ConPty::quote_arg(r"C:\dir\"), r"C:\dir\");
+        assert_eq!(ConPty::quote_arg(""), r#""""#);
+    }
+
+    #[test]
+    fn test_command_line_codepage() {
+        // The codepage is set by a separate chcp, never by wrapping the program
+        let cmd = SpawnSpec::new("cmd.exe").codepage(Some(932));
+        assert_eq!(ConPty::command_line(&cmd), "cmd.exe");
+
+        let pwsh = SpawnSpec::new("pwsh.exe").args(["-NoLogo", "a&b"]).codepage(Some(65001));
+        assert_eq!(ConPty::command_line(&pwsh), "pwsh.exe -NoLogo a&b");
+
+        assert_eq!(ConPty::command_line(&SpawnSpec::default()), "cmd.exe");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_conpty_creation() {
+        let pty = ConPty::new(80, 24, Some("cmd.exe /c echo hello"));
+        assert!(pty.is_ok());
+    }
+}
@@ -0,0 +1,230 @@
+//! In-memory PTY backend for tests
+//!
+//! `MockPty` replays canned output, records everything written to it and can
+//! simulate the child process exiting. It is a cheap handle: clone it, hand
+//! one copy to `Session::start_with_pty` and keep the other to drive the
+//! script and inspect the input the session produced.
+
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use super::{Pty, PtyError, Result, Signal, SpawnSpec};
+
+#[derive(Default)]
+struct MockState {
+    /// Output chunks waiting to be read, in order
+    output: VecDeque<Vec<u8>>,
+    /// Everything written to the PTY so far
+    written: Vec<u8>,
+    /// Exit code once the simulated process has exited
+    exit_code: Option<u32>,
+    /// Set by `close`; reads fail once the output is drained
+    closed: bool,
+    /// Last size set via spawn/resize
+    size: (u16, u16),
+    /// Signals sent so far, in order
+    signals: Vec<Signal>,
+    /// Whether input is echoed back as output, like a terminal in cooked mode
+    echo: bool,
+}
+
+/// Echoed chunks that can wait unread before writes block, like a full
+/// terminal buffer
+const ECHO_BUFFER_CHUNKS: usize = 16;
+
+/// Size of the pieces input is echoed in
+const ECHO_CHUNK_SIZE: usize = 1024;
+
+/// How long `wait_until` polls before giving up
+const WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Poll `cond` until it holds, giving the session reader thread time to run.
+///
+/// Returns whether the condition was met before the timeout.
+pub fn wait_until(mut cond: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + WAIT_TIMEOUT;
+    while Instant::now() < deadline {
+        if cond() {
+            return true;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    cond()
+}
+
+/// Scripted PTY
+#[derive(Clone, Default)]
+pub struct MockPty {
+    state: Arc<(Mutex<MockState>, Condvar)>,
+}
+
+impl MockPty {
+    /// Create a mock with no pending output
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` on the state and wake anyone blocked in `read` or `wait`
+    fn with_state<T>(&self, f: impl FnOnce(&mut MockState) -> T) -> T {
+        let (lock, cond) = &*self.state;
+        let result = f(&mut lock.lock().unwrap());
+        cond.notify_all();
+        result
+    }
+
+    /// Block until `ready` holds for the state, then run `f` on it and wake
+    /// anyone else waiting
+    fn wait_for<T>(&self, ready: impl Fn(&MockState) -> bool, f: impl FnOnce(&mut MockState) -> T) -> T {
+        let (lock, cond) = &*self.state;
+        let mut state = cond.wait_while(lock.lock().unwrap(), |s| !ready(s)).unwrap();
+        let result = f(&mut state);
+        cond.notify_all();
+        result
+    }
+
+    /// Create a mock that will produce `bytes` as its first output
+    pub fn with_output(bytes: &[u8]) -> Self {
+        let mock = Self::new();
+        mock.push_output(bytes);
+        mock
+    }
+
+    /// Create a mock that echoes its input back as output
+    ///
+    /// Writes block while too much echoed output is unread, so a session
+    /// that stops reading output while writing input deadlocks.
+    pub fn echoing() -> Self {
+        let mock = Self::new();
+        mock.with_state(|s| s.echo = true);
+        mock
+    }
+
+    /// Queue more output; each call is delivered as a separate read
+    pub fn push_output(&self, bytes: &[u8]) {
+        self.with_state(|s| s.output.push_back(bytes.to_vec()));
+    }
+
+    /// Whether queued output is still waiting to be read
+    pub fn has_pending_output(&self) -> bool {
+        self.with_state(|s| !s.output.is_empty())
+    }
+
+    /// Everything written to the PTY so far
+    pub fn written(&self) -> Vec<u8> {
+        self.with_state(|s| s.written.clone())
+    }
+
+    /// Everything written to the PTY so far, lossily decoded as UTF-8
+    pub fn written_string(&self) -> String {
+        String::from_utf8_lossy(&self.written()).into_owned()
+    }
+
+    /// Simulate the process exiting; queued output is still delivered first
+    pub fn exit(&self, code: u32) {
+        self.with_state(|s| s.exit_code = Some(code));
+    }
+
+    /// Signals sent so far, in order
+    pub fn signals(&self) -> Vec<Signal> {
+        self.with_state(|s| s.signals.clone())
+    }
+
+    /// Size from the last spawn/resize
+    pub fn size(&self) -> (u16, u16) {
+        self.with_state(|s| s.size)
+    }
+}
+
+impl Pty for MockPty {
+    fn spawn(cols: u16, rows: u16, _spec: &SpawnSpec) -> Result<Self> {
+        let mock = Self::new();
+        mock.with_state(|s| s.size = (cols, rows));
+        Ok(mock)
+    }
+
+    fn read(&self, buffer: &mut [u8]) -> Result<usize> {
+        self.wait_for(
+            |s| !s.output.is_empty() || s.closed,
+            |s| match s.output.pop_front() {
+                Some(mut chunk) => {
+                    let n = chunk.len().min(buffer.len());
+                    buffer[..n].copy_from_slice(&chunk[..n]);
+                    if n < chunk.len() {
+                        // Deliver the rest on the next read
+                        s.output.push_front(chunk.split_off(n));
+                    }
+                    Ok(n)
+                }
+                None => Err(PtyError::Read(io::Error::new(
+                    io::ErrorKind::BrokenPipe,
+                    "Mock PTY closed",
+                ))),
+            },
+        )
+    }
+
+    fn write(&self, data: &[u8]) -> Result<usize> {
+        let gone = |s: &MockState| s.exit_code.is_some() || s.closed;
+        let exited = || {
+            PtyError::Write(io::Error::new(io::ErrorKind::BrokenPipe, "Mock process exited"))
+        };
+
+        if !self.with_state(|s| s.echo) {
+            return self.with_state(|s| {
+                if gone(s) {
+                    return Err(exited());
+                }
+                s.written.extend_from_slice(data);
+                Ok(data.len())
+            });
+        }
+
+        for chunk in data.chunks(ECHO_CHUNK_SIZE) {
+            self.wait_for(
+                |s| s.output.len() < ECHO_BUFFER_CHUNKS || gone(s),
+                |s| {
+                    if gone(s) {
+                        return Err(exited());
+                    }
+                    s.written.extend_from_slice(chunk);
+                    s.output.push_back(chunk.to_vec());
+                    Ok(())
+                },
+            )?;
+        }
+        Ok(data.len())
+    }
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        self.with_state(|s| s.size = (cols, rows));
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.with_state(|s| s.exit_code.is_none())
+    }
+
+    fn exit_code(&self) -> Option<u32> {
+        self.with_state(|s| s.exit_code)
+    }
+
+    fn wait(&self) {
+        self.wait_for(|s| s.exit_code.is_some() || s.closed, |_| ());
+    }
+
+    fn signal(&self, signal: Signal) -> Result<()> {
+        self.with_state(|s| match s.exit_code {
+            Some(code) => Err(PtyError::ProcessExited(code)),
+            None => {
+                s.signals.push(signal);
+                Ok(())
+            }
+        })
+    }
+
+    fn close(&self) {
+        self.with_state(|s| s.closed = true);
+    }
+}
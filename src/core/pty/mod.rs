@@ -0,0 +1,165 @@
+//! Pseudo-terminal backends
+//!
+//! This module defines the `Pty` trait that `Session` talks to, and the
+//! platform backends implementing it:
+//!
+//! - **conpty**: Windows ConPTY (Console Pseudo Terminal)
+//! - **unix**: openpty(3) + fork/exec for Linux, WSL and macOS
+//! - **mock**: scripted in-memory backend for tests
+
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[cfg(windows)]
+mod conpty;
+#[cfg(test)]
+mod mock;
+mod spawn;
+#[cfg(unix)]
+mod unix;
+
+#[cfg(windows)]
+pub use conpty::ConPty;
+#[cfg(test)]
+pub use mock::{wait_until, MockPty};
+pub use spawn::SpawnSpec;
+#[cfg(unix)]
+pub use unix::UnixPty;
+
+/// The PTY backend for the current platform
+#[cfg(windows)]
+pub type NativePty = ConPty;
+/// The PTY backend for the current platform
+#[cfg(unix)]
+pub type NativePty = UnixPty;
+
+/// Error type reported by the operating system
+#[cfg(windows)]
+pub type OsError = windows::core::Error;
+/// Error type reported by the operating system
+#[cfg(not(windows))]
+pub type OsError = io::Error;
+
+#[derive(Error, Debug)]
+pub enum PtyError {
+    #[allow(dead_code)]
+    #[error("Failed to create pipe: {0}")]
+    PipeCreation(#[source] OsError),
+
+    #[error("Failed to create pseudo terminal: {0}")]
+    PtyCreation(#[source] OsError),
+
+    #[error("Failed to spawn process: {0}")]
+    ProcessSpawn(#[source] OsError),
+
+    #[error("Failed to resize pseudo terminal: {0}")]
+    Resize(#[source] OsError),
+
+    #[error("Failed to read from PTY: {0}")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to write to PTY: {0}")]
+    Write(#[source] io::Error),
+
+    #[error("Failed to signal process: {0}")]
+    Signal(#[source] OsError),
+
+    #[error("Process has exited with code: {0}")]
+    ProcessExited(u32),
+
+    #[error("Invalid handle")]
+    InvalidHandle,
+}
+
+pub type Result<T> = std::result::Result<T, PtyError>;
+
+/// Signal that can be sent to the processes in a pseudo terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Interrupt the foreground job (SIGINT, Ctrl+C)
+    Interrupt,
+    /// Ask the processes to exit (SIGTERM, Ctrl+Break)
+    Terminate,
+    /// Force the processes to exit (SIGKILL, TerminateProcess)
+    Kill,
+}
+
+impl Signal {
+    /// Name of the signal as delivered on this platform
+    pub fn name(&self) -> &'static str {
+        if cfg!(windows) {
+            match self {
+                Signal::Interrupt => "Ctrl+C",
+                Signal::Terminate => "Ctrl+Break",
+                Signal::Kill => "TerminateProcess",
+            }
+        } else {
+            match self {
+                Signal::Interrupt => "SIGINT",
+                Signal::Terminate => "SIGTERM",
+                Signal::Kill => "SIGKILL",
+            }
+        }
+    }
+}
+
+/// A pseudo terminal connected to a child process
+///
+/// All methods take `&self` so a backend can be shared between the session
+/// (writes, resizes) and its reader thread through an `Arc`.
+pub trait Pty: Send + Sync {
+    /// Create a pseudo terminal of the given size and spawn a process in it.
+    ///
+    /// The program defaults to the platform shell. The codepage is only
+    /// meaningful for ConPTY and is ignored by other backends.
+    fn spawn(cols: u16, rows: u16, spec: &SpawnSpec) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Read bytes from the PTY (output from the process), blocking until
+    /// some are available.
+    ///
+    /// May return `Ok(0)` on a spurious wakeup. Returns an error once the
+    /// PTY has been closed and all buffered output has been read.
+    fn read(&self, buffer: &mut [u8]) -> Result<usize>;
+
+    /// Write bytes to the PTY (input to the process)
+    fn write(&self, data: &[u8]) -> Result<usize>;
+
+    /// Resize the pseudo terminal
+    fn resize(&self, cols: u16, rows: u16) -> Result<()>;
+
+    /// Check if the process is still running
+    fn is_running(&self) -> bool;
+
+    /// Get the exit code if the process has exited
+    fn exit_code(&self) -> Option<u32>;
+
+    /// Block until the process has exited
+    fn wait(&self);
+
+    /// Send a signal to the processes in the terminal.
+    ///
+    /// `Interrupt` goes to the foreground job only, like typing Ctrl+C.
+    /// Fails with `ProcessExited` once the process has exited.
+    fn signal(&self, signal: Signal) -> Result<()>;
+
+    /// Working directory of the terminal's foreground process, if the
+    /// backend can find out
+    fn foreground_cwd(&self) -> Option<PathBuf> {
+        None
+    }
+
+    /// Command name of the terminal's foreground process, if the backend
+    /// can find out
+    fn foreground_name(&self) -> Option<String> {
+        None
+    }
+
+    /// Hang up the pseudo terminal.
+    ///
+    /// The process is told to exit if it is still running, and a blocked
+    /// `read` returns an error once the output buffered so far is drained.
+    fn close(&self);
+}
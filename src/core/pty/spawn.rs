@@ -0,0 +1,216 @@
+//! Spawn specification for pane processes
+
+use std::path::PathBuf;
+
+/// Environment variable holding the tab ID of a pane's process
+const TAB_ENV: &str = "WTMUX_TAB";
+/// Environment variable holding the pane ID of a pane's process
+const PANE_ENV: &str = "WTMUX_PANE";
+
+/// What to run in a pseudo terminal and how
+///
+/// Backends turn this into a process directly; no part of it is
+/// interpreted by a shell.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpawnSpec {
+    /// Program to run; `None` runs the platform default shell
+    pub program: Option<String>,
+    /// Arguments passed to the program
+    pub args: Vec<String>,
+    /// Working directory; `None` inherits wtmux's own
+    pub cwd: Option<PathBuf>,
+    /// Environment variables set on top of wtmux's own environment
+    pub env: Vec<(String, String)>,
+    /// Console codepage to run the program with (ConPTY only)
+    pub codepage: Option<u32>,
+    /// Initial pane title
+    pub title: Option<String>,
+}
+
+impl SpawnSpec {
+    /// Run `program` with no arguments
+    #[allow(dead_code)]
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: Some(program.into()),
+            ..Self::default()
+        }
+    }
+
+    /// Parse a command line such as `pwsh.exe -NoLogo` from the config or CLI
+    ///
+    /// Arguments are separated by whitespace; double quotes group words
+    /// (e.g. `"C:\Program Files\PowerShell\7\pwsh.exe"`). An empty command
+    /// line selects the default shell.
+    pub fn from_command_line(command: &str) -> Self {
+        let mut words = split_command_line(command).into_iter();
+        Self {
+            program: words.next(),
+            args: words.collect(),
+            ..Self::default()
+        }
+    }
+
+    /// Replace the program and arguments with those of a command line
+    pub fn with_command_line(mut self, command: &str) -> Self {
+        let parsed = Self::from_command_line(command);
+        self.program = parsed.program;
+        self.args = parsed.args;
+        self
+    }
+
+    /// The program and arguments as a command line for display and editing
+    ///
+    /// Words with whitespace are quoted, so the result parses back with
+    /// `from_command_line` unless a word itself contains a double quote.
+    pub fn command_line(&self) -> String {
+        self.program
+            .iter()
+            .chain(&self.args)
+            .map(|word| {
+                if word.is_empty() || word.contains(char::is_whitespace) {
+                    format!("\"{}\"", word)
+                } else {
+                    word.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Set the arguments
+    #[allow(dead_code)]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Set the working directory
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Add an environment variable
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Set the console codepage
+    pub fn codepage(mut self, codepage: Option<u32>) -> Self {
+        self.codepage = codepage;
+        self
+    }
+
+    /// Tell the program which pane it runs in
+    ///
+    /// Sets `WTMUX_TAB` and `WTMUX_PANE`, like tmux's `TMUX_PANE`, so shell
+    /// prompts and scripts can target their own pane.
+    pub fn pane_identity(self, tab: u64, pane: u64) -> Self {
+        self.env(TAB_ENV, tab.to_string())
+            .env(PANE_ENV, pane.to_string())
+    }
+
+    /// Set the initial pane title
+    #[allow(dead_code)]
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+}
+
+/// Split a command line into words, honouring double quotes
+fn split_command_line(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+
+    for ch in command.chars() {
+        match ch {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_command_line() {
+        let spec = SpawnSpec::from_command_line("pwsh.exe -NoLogo  -NoProfile");
+        assert_eq!(spec.program.as_deref(), Some("pwsh.exe"));
+        assert_eq!(spec.args, vec!["-NoLogo", "-NoProfile"]);
+
+        let spec = SpawnSpec::from_command_line(r#""C:\Program Files\PowerShell\7\pwsh.exe" -c "echo hi""#);
+        assert_eq!(spec.program.as_deref(), Some(r"C:\Program Files\PowerShell\7\pwsh.exe"));
+        assert_eq!(spec.args, vec!["-c", "echo hi"]);
+
+        assert_eq!(SpawnSpec::from_command_line("  "), SpawnSpec::default());
+        assert_eq!(SpawnSpec::from_command_line(r#"sh -c """#).args, vec!["-c", ""]);
+    }
+
+    #[test]
+    fn test_command_line_round_trip() {
+        let spec = SpawnSpec::new(r"C:\Program Files\Git\bin\bash.exe").args(["-c", "make test", ""]);
+        let line = spec.command_line();
+        assert_eq!(line, r#""C:\Program Files\Git\bin\bash.exe" -c "make test" """#);
+        assert_eq!(SpawnSpec::from_command_line(&line), spec);
+
+        let respawned = spec.clone().env("FOO", "bar").with_command_line("cargo run");
+        assert_eq!(respawned.program.as_deref(), Some("cargo"));
+        assert_eq!(respawned.args, vec!["run"]);
+        assert_eq!(respawned.env, vec![("FOO".to_string(), "bar".to_string())]);
+        assert_eq!(SpawnSpec::default().command_line(), "");
+    }
+
+    #[test]
+    fn test_builder() {
+        let spec = SpawnSpec::new("sh")
+            .args(["-c", "pwd"])
+            .cwd("/tmp")
+            .env("FOO", "bar")
+            .codepage(Some(932));
+        assert_eq!(spec.program.as_deref(), Some("sh"));
+        assert_eq!(spec.args, vec!["-c", "pwd"]);
+        assert_eq!(spec.cwd, Some(PathBuf::from("/tmp")));
+        assert_eq!(spec.env, vec![("FOO".to_string(), "bar".to_string())]);
+        assert_eq!(spec.codepage, Some(932));
+    }
+
+    #[test]
+    fn test_pane_identity() {
+        let spec = SpawnSpec::default().env("FOO", "bar").pane_identity(3, 7);
+        assert_eq!(
+            spec.env,
+            vec![
+                ("FOO".to_string(), "bar".to_string()),
+                ("WTMUX_TAB".to_string(), "3".to_string()),
+                ("WTMUX_PANE".to_string(), "7".to_string()),
+            ]
+        );
+    }
+}
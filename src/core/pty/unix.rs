@@ -0,0 +1,395 @@
+//! Unix PTY backend
+//!
+//! Allocates a pseudo terminal pair with openpty(3) and spawns the child
+//! process on the slave side as a new session leader, so job control and
+//! SIGWINCH behave as they would in any other terminal emulator.
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+use super::{Pty, PtyError, Result, Signal, SpawnSpec};
+
+/// Shell used when no command is given and `$SHELL` is unset
+const FALLBACK_SHELL: &str = "/bin/sh";
+
+/// openpty/fork based PTY
+pub struct UnixPty {
+    /// Master side of the pseudo terminal
+    master: File,
+    /// Child process running on the slave side
+    child: Mutex<Child>,
+    /// Process ID of the child (also its process group and session ID)
+    pid: libc::pid_t,
+    /// Self-pipe used to wake a blocked `read` on `close`
+    close_rx: File,
+    close_tx: File,
+}
+
+impl UnixPty {
+    /// Set FD_CLOEXEC so the descriptor doesn't leak into other panes' children
+    fn set_cloexec(fd: RawFd) {
+        unsafe {
+            let flags = libc::fcntl(fd, libc::F_GETFD);
+            if flags != -1 {
+                libc::fcntl(fd, libc::F_SETFD, flags | libc::FD_CLOEXEC);
+            }
+        }
+    }
+
+    /// Create a close-on-exec pipe, returning (read end, write end)
+    fn pipe() -> io::Result<(File, File)> {
+        let mut fds: [RawFd; 2] = [-1; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Self::set_cloexec(fds[0]);
+        Self::set_cloexec(fds[1]);
+        // Safety: pipe succeeded, so both descriptors are valid and owned by us
+        unsafe { Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))) }
+    }
+
+    fn winsize(cols: u16, rows: u16) -> libc::winsize {
+        libc::winsize {
+            ws_row: rows,
+            ws_col: cols,
+            ws_xpixel: 0,
+            ws_ypixel: 0,
+        }
+    }
+
+    /// Process group in the foreground of the terminal, else the child's
+    fn foreground_pid(&self) -> libc::pid_t {
+        let pgrp = unsafe { libc::tcgetpgrp(self.master.as_raw_fd()) };
+        if pgrp > 0 {
+            pgrp
+        } else {
+            self.pid
+        }
+    }
+
+    /// Build the command to run: the given program, or the user's shell
+    fn build_command(spec: &SpawnSpec) -> Command {
+        let program = spec
+            .program
+            .clone()
+            .or_else(|| std::env::var("SHELL").ok())
+            .filter(|s| !s.trim().is_empty())
+            .unwrap_or_else(|| FALLBACK_SHELL.to_string());
+
+        let mut cmd = Command::new(program);
+        cmd.args(&spec.args);
+        if let Some(cwd) = &spec.cwd {
+            cmd.current_dir(cwd);
+        }
+        cmd.envs(spec.env.iter().map(|(k, v)| (k, v)));
+        cmd
+    }
+}
+
+impl Pty for UnixPty {
+    fn spawn(cols: u16, rows: u16, spec: &SpawnSpec) -> Result<Self> {
+        let mut master_fd: RawFd = -1;
+        let mut slave_fd: RawFd = -1;
+        let size = Self::winsize(cols, rows);
+
+        let ret = unsafe {
+            libc::openpty(
+                &mut master_fd,
+                &mut slave_fd,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                // Some platforms declare this parameter as *mut
+                std::ptr::addr_of!(size) as *mut _,
+            )
+        };
+        if ret != 0 {
+            return Err(PtyError::PtyCreation(io::Error::last_os_error()));
+        }
+
+        // Safety: openpty succeeded, so both descriptors are valid and owned by us
+        let master = unsafe { File::from_raw_fd(master_fd) };
+        let slave = unsafe { OwnedFd::from_raw_fd(slave_fd) };
+        Self::set_cloexec(master.as_raw_fd());
+        Self::set_cloexec(slave.as_raw_fd());
+
+        let (close_rx, close_tx) = Self::pipe().map_err(PtyError::PtyCreation)?;
+
+        let stdin = slave.try_clone().map_err(PtyError::PtyCreation)?;
+        let stdout = slave.try_clone().map_err(PtyError::PtyCreation)?;
+
+        let mut cmd = Self::build_command(spec);
+        cmd.stdin(Stdio::from(stdin))
+            .stdout(Stdio::from(stdout))
+            .stderr(Stdio::from(slave))
+            .env("TERM", "xterm-256color");
+
+        unsafe {
+            cmd.pre_exec(|| {
+                // Become a session leader and take the slave as controlling terminal
+                if libc::setsid() == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                if libc::ioctl(0, libc::TIOCSCTTY as _, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+
+        let child = cmd.spawn().map_err(PtyError::ProcessSpawn)?;
+
+        Ok(UnixPty {
+            master,
+            pid: child.id() as libc::pid_t,
+            child: Mutex::new(child),
+            close_rx,
+            close_tx,
+        })
+    }
+
+    /// Read bytes from the PTY (output from shell), blocking until available
+    fn read(&self, buffer: &mut [u8]) -> Result<usize> {
+        let mut fds = [
+            libc::pollfd {
+                fd: self.master.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: self.close_rx.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+
+        let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, -1) };
+        if ready < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                return Ok(0);
+            }
+            return Err(PtyError::Read(err));
+        }
+
+        // Buffered output takes priority over a pending close
+        if fds[0].revents == 0 {
+            return Err(PtyError::Read(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "PTY closed",
+            )));
+        }
+
+        // POLLHUP/POLLERR without data also ends up here; read reports it
+        match (&self.master).read(buffer) {
+            Ok(0) => Err(PtyError::Read(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "PTY closed",
+            ))),
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => Ok(0),
+            // Linux reports EIO once the slave side has been closed
+            Err(e) => Err(PtyError::Read(e)),
+        }
+    }
+
+    fn write(&self, data: &[u8]) -> Result<usize> {
+        (&self.master).write(data).map_err(PtyError::Write)
+    }
+
+    fn resize(&self, cols: u16, rows: u16) -> Result<()> {
+        let size = Self::winsize(cols, rows);
+        let ret = unsafe { libc::ioctl(self.master.as_raw_fd(), libc::TIOCSWINSZ as _, &size) };
+        if ret == -1 {
+            return Err(PtyError::Resize(io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        match self.child.lock() {
+            Ok(mut child) => matches!(child.try_wait(), Ok(None)),
+            Err(_) => false,
+        }
+    }
+
+    fn exit_code(&self) -> Option<u32> {
+        let status = self.child.lock().ok()?.try_wait().ok()??;
+        // Follow the shell convention of 128 + N for death by signal N
+        status
+            .code()
+            .map(|code| code as u32)
+            .or_else(|| status.signal().map(|sig| 128 + sig as u32))
+    }
+
+    fn wait(&self) {
+        loop {
+            // WNOWAIT leaves the child for `try_wait` to reap
+            let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+            let ret = unsafe {
+                libc::waitid(
+                    libc::P_PID,
+                    self.pid as libc::id_t,
+                    &mut info,
+                    libc::WEXITED | libc::WNOWAIT,
+                )
+            };
+            // ECHILD means it has already been reaped
+            if ret == 0 || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                break;
+            }
+        }
+    }
+
+    fn signal(&self, signal: Signal) -> Result<()> {
+        // Once reaped, the process group ID may belong to someone else
+        if let Some(code) = self.exit_code() {
+            return Err(PtyError::ProcessExited(code));
+        }
+
+        let signum = match signal {
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Kill => libc::SIGKILL,
+        };
+        let foreground = self.foreground_pid();
+        let mut groups = vec![foreground];
+        if signal != Signal::Interrupt && foreground != self.pid {
+            // Also reach the shell that started the foreground job
+            groups.push(self.pid);
+        }
+        for pgrp in groups {
+            if unsafe { libc::kill(-pgrp, signum) } == -1 {
+                return Err(PtyError::Signal(io::Error::last_os_error()));
+            }
+        }
+        Ok(())
+    }
+
+    fn foreground_cwd(&self) -> Option<PathBuf> {
+        // Linux only; other systems have no /proc
+        std::fs::read_link(format!("/proc/{}/cwd", self.foreground_pid())).ok()
+    }
+
+    fn foreground_name(&self) -> Option<String> {
+        let comm = std::fs::read_to_string(format!("/proc/{}/comm", self.foreground_pid())).ok()?;
+        Some(comm.trim_end().to_string()).filter(|name| !name.is_empty())
+    }
+
+    fn close(&self) {
+        if self.is_running() {
+            // Hang up the whole session, like closing a terminal window
+            unsafe {
+                libc::kill(-self.pid, libc::SIGHUP);
+            }
+        }
+        // Wake the reader even if a background job still holds the slave open
+        let _ = (&self.close_tx).write(&[0]);
+    }
+}
+
+impl Drop for UnixPty {
+    fn drop(&mut self) {
+        if let Ok(child) = self.child.get_mut() {
+            if let Ok(None) = child.try_wait() {
+                unsafe {
+                    libc::kill(-self.pid, libc::SIGHUP);
+                }
+                let _ = child.try_wait();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Read until the PTY closes
+    fn read_to_end(pty: &UnixPty) -> String {
+        let mut output = Vec::new();
+        let mut buffer = [0u8; 256];
+        while let Ok(n) = pty.read(&mut buffer) {
+            output.extend_from_slice(&buffer[..n]);
+        }
+        String::from_utf8_lossy(&output).into_owned()
+    }
+
+    #[test]
+    fn test_unix_pty_creation() {
+        let pty = UnixPty::spawn(80, 24, &SpawnSpec::new("echo").args(["hello"]));
+        assert!(pty.is_ok());
+    }
+
+    #[test]
+    fn test_unix_pty_exit_code() {
+        let pty = UnixPty::spawn(80, 24, &SpawnSpec::new("false")).unwrap();
+
+        pty.wait();
+        assert!(!pty.is_running());
+        assert_eq!(pty.exit_code(), Some(1));
+    }
+
+    #[test]
+    fn test_unix_pty_read_until_exit() {
+        let pty = UnixPty::spawn(80, 24, &SpawnSpec::new("echo").args(["hello"])).unwrap();
+        assert!(read_to_end(&pty).contains("hello"));
+    }
+
+    #[test]
+    fn test_unix_pty_spawn_spec() {
+        let dir = std::env::temp_dir().canonicalize().unwrap();
+        let spec = SpawnSpec::new("sh")
+            .args(["-c", "echo \"$WTMUX_TEST_VAR\" in \"$(pwd -P)\""])
+            .cwd(&dir)
+            .env("WTMUX_TEST_VAR", "a b");
+        let pty = UnixPty::spawn(80, 24, &spec).unwrap();
+
+        let output = read_to_end(&pty);
+        assert!(output.contains(&format!("a b in {}", dir.display())), "{:?}", output);
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_unix_pty_foreground_process() {
+        let dir = std::env::temp_dir().canonicalize().unwrap();
+        let pty = UnixPty::spawn(80, 24, &SpawnSpec::new("sleep").args(["5"]).cwd(&dir)).unwrap();
+
+        assert!(crate::core::pty::wait_until(|| pty.foreground_cwd() == Some(dir.clone())));
+        assert!(crate::core::pty::wait_until(|| pty.foreground_name().as_deref() == Some("sleep")));
+        pty.close();
+    }
+
+    #[test]
+    fn test_unix_pty_signal() {
+        let pty = UnixPty::spawn(80, 24, &SpawnSpec::new("sleep").args(["30"])).unwrap();
+
+        pty.signal(Signal::Terminate).unwrap();
+        pty.wait();
+        assert_eq!(pty.exit_code(), Some(128 + libc::SIGTERM as u32));
+        assert!(matches!(pty.signal(Signal::Kill), Err(PtyError::ProcessExited(_))));
+    }
+
+    #[test]
+    fn test_unix_pty_close_unblocks_read() {
+        let pty = Arc::new(UnixPty::spawn(80, 24, &SpawnSpec::new("sleep").args(["30"])).unwrap());
+
+        let reader = {
+            let pty = pty.clone();
+            thread::spawn(move || {
+                let mut buffer = [0u8; 256];
+                while pty.read(&mut buffer).is_ok() {}
+            })
+        };
+        pty.close();
+        reader.join().unwrap();
+        pty.wait();
+        assert!(!pty.is_running());
+    }
+}
@@ -2,13 +2,28 @@
 //!
 //! Manages shell sessions, handling I/O between PTY and terminal state.
 
+use std::io;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{self, Receiver, TryRecvError};
-use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 
-use super::pty::{ConPty, PtyError};
+use super::asciicast::Recorder;
+use super::encoding::{Decoder, Encoding};
+use super::pipe::{OutputPipe, PipeTarget};
+use super::pty::{NativePty, Pty, PtyError, Signal, SpawnSpec};
 use super::term::{Response, TerminalState, VtParser};
+use crate::event_loop::{AppEvent, EventSender};
+
+/// Output chunks buffered between the reader thread and the main loop.
+///
+/// Once full the reader blocks, the PTY buffer fills up and the process is
+/// throttled instead of wtmux buffering a flood of output in memory.
+const OUTPUT_CHANNEL_CAPACITY: usize = 64;
+
+/// Bytes of output processed per pane before a frame is drawn
+pub const OUTPUT_BYTES_PER_FRAME: usize = 64 * 1024;
 
 /// Session events
 #[allow(dead_code)]
@@ -33,23 +48,30 @@ pub struct Session {
     pub state: TerminalState,
     /// VT parser
     parser: VtParser,
-    /// PTY handle (Windows only)
-    #[cfg(windows)]
-    pty: Option<Arc<ConPty>>,
+    /// Encoding of PTY output and input
+    encoding: Encoding,
+    /// Decoder for PTY output
+    decoder: Decoder,
+    /// PTY handle
+    pty: Option<Arc<dyn Pty>>,
     /// Running flag
     running: Arc<AtomicBool>,
     /// Reader thread handle
-    #[cfg(windows)]
     reader_thread: Option<JoinHandle<()>>,
     /// Channel to receive PTY output
-    #[cfg(windows)]
     output_rx: Option<Receiver<Vec<u8>>>,
+    /// Input waiting for the writer thread
+    input_tx: Option<Sender<Vec<u8>>>,
+    /// Where to report new output and process exit
+    events: Option<EventSender>,
+    /// Set while a `PtyOutput` event is waiting to be handled
+    output_pending: Arc<AtomicBool>,
+    /// Where the reader thread copies raw output to (pipe-pane)
+    output_pipe: Arc<Mutex<Option<OutputPipe>>>,
+    /// asciicast recording of output and resizes
+    recorder: Arc<Mutex<Option<Recorder>>>,
 }
 
-// ConPty needs to be Send + Sync for Arc
-#[cfg(windows)]
-unsafe impl Sync for ConPty {}
-
 impl Session {
     /// Create a new session
     pub fn new(id: u64, cols: u16, rows: u16) -> Self {
@@ -57,80 +79,124 @@ impl Session {
             id,
             state: TerminalState::new(cols, rows),
             parser: VtParser::new(),
-            #[cfg(windows)]
+            encoding: Encoding::Utf8,
+            decoder: Encoding::Utf8.decoder(),
             pty: None,
             running: Arc::new(AtomicBool::new(false)),
-            #[cfg(windows)]
             reader_thread: None,
-            #[cfg(windows)]
             output_rx: None,
+            input_tx: None,
+            events: None,
+            output_pending: Arc::new(AtomicBool::new(false)),
+            output_pipe: Arc::new(Mutex::new(None)),
+            recorder: Arc::new(Mutex::new(None)),
         }
     }
 
-    /// Start the session with a shell command
-    #[cfg(windows)]
-    #[allow(dead_code)]
-    pub fn start(&mut self, command: Option<&str>) -> Result<(), PtyError> {
-        self.start_with_codepage(command, None)
+    /// Report output and exit of this session to an event loop.
+    ///
+    /// Must be called before the session is started.
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        self.events = Some(events);
     }
 
-    /// Start the session with a shell command and specific codepage
-    #[cfg(windows)]
-    pub fn start_with_codepage(&mut self, command: Option<&str>, codepage: Option<u32>) -> Result<(), PtyError> {
+    /// Spawn the process described by `spec` and start the session
+    pub fn start(&mut self, spec: &SpawnSpec) -> Result<(), PtyError> {
         let (cols, rows) = (self.state.cols, self.state.rows);
-        let pty = Arc::new(ConPty::new_with_codepage(cols, rows, command, codepage)?);
+        let pty = NativePty::spawn(cols, rows, spec)?;
+        self.set_encoding(Encoding::for_pty_codepage(spec.codepage));
+        self.start_with_pty(Arc::new(pty));
+        Ok(())
+    }
+
+    /// Start the session on an already spawned PTY and start the reader thread
+    pub fn start_with_pty(&mut self, pty: Arc<dyn Pty>) {
         self.pty = Some(pty.clone());
         self.running.store(true, Ordering::SeqCst);
 
         // Create channel for PTY output
-        let (tx, rx) = mpsc::channel::<Vec<u8>>();
+        let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(OUTPUT_CHANNEL_CAPACITY);
         self.output_rx = Some(rx);
 
-        // Spawn reader thread
+        // Spawn reader thread; reads block until output arrives
         let running = self.running.clone();
+        let events = self.events.clone();
+        let output_pending = self.output_pending.clone();
+        let output_pipe = self.output_pipe.clone();
+        let recorder = self.recorder.clone();
+        let reader_pty = pty.clone();
         let reader_thread = thread::spawn(move || {
             let mut buffer = vec![0u8; 4096];
 
-            loop {
-                // First check if we should stop
-                if !running.load(Ordering::SeqCst) {
-                    break;
-                }
-
-                // Check if process is still running
-                if !pty.is_running() {
-                    running.store(false, Ordering::SeqCst);
-                    break;
-                }
-
-                match pty.read(&mut buffer) {
+            while running.load(Ordering::SeqCst) {
+                match reader_pty.read(&mut buffer) {
                     Ok(0) => {
-                        // No data available (non-blocking), sleep and retry
-                        thread::sleep(std::time::Duration::from_millis(5));
+                        // Spurious wakeup, read again
                     }
                     Ok(n) => {
-                        // Send data to main thread
+                        // Copy it for pipe-pane; stop piping once that fails
+                        if let Ok(mut pipe) = output_pipe.lock() {
+                            if pipe.as_mut().is_some_and(|pipe| pipe.write(&buffer[..n]).is_err()) {
+                                *pipe = None;
+                            }
+                        }
+                        // Timestamped here rather than when the main loop gets to it
+                        if let Ok(mut recorder) = recorder.lock() {
+                            if recorder.as_mut().is_some_and(|rec| rec.output(&buffer[..n]).is_err()) {
+                                *recorder = None;
+                            }
+                        }
+
+                        // Send data to main thread, waiting while the channel is full
                         if tx.send(buffer[..n].to_vec()).is_err() {
-                            running.store(false, Ordering::SeqCst);
                             break;
                         }
+                        // Wake the main loop once; process_output re-arms this
+                        if !output_pending.swap(true, Ordering::SeqCst) {
+                            if let Some(events) = &events {
+                                let _ = events.send(AppEvent::PtyOutput);
+                            }
+                        }
                     }
                     Err(_) => {
-                        // Read error - pipe closed or process exited
-                        running.store(false, Ordering::SeqCst);
+                        // PTY closed - process exited and its output is drained
                         break;
                     }
                 }
             }
+
+            running.store(false, Ordering::SeqCst);
+            if let Some(events) = &events {
+                let _ = events.send(AppEvent::PtyExited);
+            }
         });
 
-        self.reader_thread = Some(reader_thread);
-        Ok(())
-    }
+        // Spawn writer thread. A process that stops reading input until its
+        // output is read (e.g. echoing a large paste while the output channel
+        // is full) must not block the main loop, which does that reading.
+        let (input_tx, input_rx) = mpsc::channel::<Vec<u8>>();
+        self.input_tx = Some(input_tx);
+        let writer_pty = pty.clone();
+        thread::spawn(move || {
+            for data in input_rx {
+                let mut rest = &data[..];
+                while !rest.is_empty() {
+                    match writer_pty.write(rest) {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => rest = &rest[n..],
+                    }
+                }
+            }
+        });
+
+        // Hang up the PTY once the process exits, so the reader sees the end
+        // of the output even if a background job keeps the terminal open
+        thread::spawn(move || {
+            pty.wait();
+            pty.close();
+        });
 
-    #[cfg(not(windows))]
-    pub fn start(&mut self, _command: Option<&str>) -> Result<(), String> {
-        Err("PTY is only supported on Windows".to_string())
+        self.reader_thread = Some(reader_thread);
     }
 
     /// Check if session is running
@@ -138,38 +204,129 @@ impl Session {
         self.running.load(Ordering::SeqCst)
     }
 
-    /// Write input to the PTY
-    #[cfg(windows)]
-    pub fn write(&self, data: &[u8]) -> Result<usize, PtyError> {
-        if let Some(pty) = &self.pty {
-            pty.write(data)
-        } else {
-            Err(PtyError::InvalidHandle)
+    /// Current working directory of the pane
+    ///
+    /// Uses the directory reported by the shell via OSC 7, else asks the
+    /// backend about the foreground process.
+    pub fn cwd(&self) -> Option<PathBuf> {
+        self.state
+            .cwd
+            .clone()
+            .or_else(|| self.pty.as_ref()?.foreground_cwd())
+    }
+
+    /// Command name of the foreground process, if the backend can tell
+    pub fn foreground_name(&self) -> Option<String> {
+        self.pty.as_ref()?.foreground_name()
+    }
+
+    /// Get the exit code once the process has exited
+    pub fn exit_code(&self) -> Option<u32> {
+        self.pty.as_ref()?.exit_code()
+    }
+
+    /// Copy all output read from now on to `pipe`, or stop copying with `None`
+    pub fn set_output_pipe(&self, pipe: Option<OutputPipe>) {
+        if let Ok(mut current) = self.output_pipe.lock() {
+            *current = pipe;
         }
     }
 
-    #[cfg(not(windows))]
-    pub fn write(&self, _data: &[u8]) -> Result<usize, String> {
-        Err("PTY is only supported on Windows".to_string())
+    /// Stop copying output, handing back the pipe
+    pub fn take_output_pipe(&self) -> Option<OutputPipe> {
+        self.output_pipe.lock().ok()?.take()
     }
 
-    /// Read and process output from PTY (non-blocking)
-    #[cfg(windows)]
-    pub fn process_output(&mut self) -> Result<bool, PtyError> {
-        // Check if PTY process is still running
-        if let Some(pty) = &self.pty {
-            if !pty.is_running() {
-                self.running.store(false, Ordering::SeqCst);
+    /// Where output is being copied to, if anywhere
+    pub fn output_pipe_target(&self) -> Option<PipeTarget> {
+        Some(self.output_pipe.lock().ok()?.as_ref()?.target().clone())
+    }
+
+    /// Record output and resizes from now on with `recorder`, or stop
+    /// recording with `None`
+    pub fn set_recorder(&self, recorder: Option<Recorder>) {
+        if let Ok(mut current) = self.recorder.lock() {
+            *current = recorder;
+        }
+    }
+
+    /// Stop recording, handing back the recorder
+    pub fn take_recorder(&self) -> Option<Recorder> {
+        self.recorder.lock().ok()?.take()
+    }
+
+    /// File the session is being recorded to, if any
+    pub fn recording_path(&self) -> Option<PathBuf> {
+        Some(self.recorder.lock().ok()?.as_ref()?.path().to_path_buf())
+    }
+
+    /// Send a signal to the session's processes
+    pub fn signal(&self, signal: Signal) -> Result<(), PtyError> {
+        self.pty.as_ref().ok_or(PtyError::InvalidHandle)?.signal(signal)
+    }
+
+    /// Get the encoding of PTY output and input
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Change the encoding of PTY output and input
+    ///
+    /// A partially received character in the old encoding is discarded.
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+        self.decoder = encoding.decoder();
+        if let Ok(mut recorder) = self.recorder.lock() {
+            if let Some(recorder) = recorder.as_mut() {
+                recorder.set_encoding(encoding);
             }
         }
-        
-        // First, collect all available data from the channel
+    }
+
+    /// Write UTF-8 input to the PTY, converted to the session encoding
+    ///
+    /// The input is queued for the writer thread, so this never blocks. It
+    /// fails once an earlier write has failed, e.g. after the process exited.
+    pub fn write(&self, data: &[u8]) -> Result<usize, PtyError> {
+        self.queue_input(self.encoding.encode(data).into_owned())
+    }
+
+    /// Queue raw bytes for the writer thread
+    fn queue_input(&self, bytes: Vec<u8>) -> Result<usize, PtyError> {
+        let input_tx = self.input_tx.as_ref().ok_or(PtyError::InvalidHandle)?;
+        let len = bytes.len();
+        input_tx.send(bytes).map_err(|_| {
+            PtyError::Write(io::Error::new(io::ErrorKind::BrokenPipe, "PTY input closed"))
+        })?;
+        Ok(len)
+    }
+
+    /// Read and process output from PTY (non-blocking), stopping once at
+    /// least `max_bytes` have been processed.
+    ///
+    /// Output left over wakes the event loop again, so it is processed
+    /// after the next frame is drawn.
+    pub fn process_output(&mut self, max_bytes: usize) -> Result<bool, PtyError> {
+        // Process exit is detected by the reader thread once it has drained
+        // the remaining output, so the running flag is not touched here
+
+        // Re-arm the output notification before draining, so output arriving
+        // from here on wakes the main loop again
+        self.output_pending.store(false, Ordering::SeqCst);
+
+        // First, collect available data from the channel up to the budget
         let mut all_data: Vec<Vec<u8>> = Vec::new();
+        let mut total = 0;
         
         if let Some(rx) = &self.output_rx {
             loop {
+                if total >= max_bytes {
+                    self.wake_for_pending_output();
+                    break;
+                }
                 match rx.try_recv() {
                     Ok(data) => {
+                        total += data.len();
                         all_data.push(data);
                     }
                     Err(TryRecvError::Empty) => {
@@ -194,88 +351,54 @@ impl Session {
         Ok(processed)
     }
 
-    #[cfg(not(windows))]
-    pub fn process_output(&mut self) -> Result<bool, String> {
-        Ok(false)
+    /// Send an output event for output that is still waiting, unless one is
+    /// already on its way
+    fn wake_for_pending_output(&self) {
+        if !self.output_pending.swap(true, Ordering::SeqCst) {
+            if let Some(events) = &self.events {
+                let _ = events.send(AppEvent::PtyOutput);
+            }
+        }
     }
 
     /// Feed raw bytes into the terminal
     pub fn feed_bytes(&mut self, bytes: &[u8]) {
-        // ConPTY always outputs UTF-8
-        // Process byte by byte, handling UTF-8 sequences
-        let mut i = 0;
-        while i < bytes.len() {
-            let b = bytes[i];
-            
-            // Control characters and escape sequences
-            if b < 0x20 || b == 0x7f {
-                if let Some(response) = self.parser.feed(b, &mut self.state) {
-                    self.send_response(response);
-                }
-                i += 1;
-                continue;
-            }
-            
-            // ASCII printable
-            if b < 0x80 {
-                if let Some(response) = self.parser.feed(b, &mut self.state) {
-                    self.send_response(response);
-                }
-                i += 1;
-                continue;
+        // The decoder carries sequences split across reads over to the next call
+        let text = self.decoder.decode(bytes);
+        for ch in text.chars() {
+            if let Some(response) = self.parser.feed_char(ch, &mut self.state) {
+                self.send_response(response);
             }
-            
-            // UTF-8 multi-byte sequence
-            let seq_len = if b & 0xE0 == 0xC0 { 2 }
-                else if b & 0xF0 == 0xE0 { 3 }
-                else if b & 0xF8 == 0xF0 { 4 }
-                else { 1 }; // Invalid, skip
-            
-            if i + seq_len <= bytes.len() {
-                if let Ok(s) = std::str::from_utf8(&bytes[i..i+seq_len]) {
-                    for ch in s.chars() {
-                        self.state.put_char(ch);
-                    }
-                    i += seq_len;
-                    continue;
-                }
-            }
-            
-            // Invalid or incomplete sequence, skip byte
-            i += 1;
         }
     }
 
-    /// Send a response back to the PTY
+    /// Send a response back to the PTY, in order with the input
     fn send_response(&self, response: Response) {
-        let bytes = response.to_bytes();
-
-        #[cfg(windows)]
-        if let Some(pty) = &self.pty {
-            let _ = pty.write(&bytes);
-        }
+        let _ = self.queue_input(response.to_bytes());
     }
 
     /// Resize the terminal
-    #[cfg(windows)]
     pub fn resize(&mut self, cols: u16, rows: u16) -> Result<(), PtyError> {
+        // Layout passes resize panes to the size they already have
+        if (cols, rows) != (self.state.cols, self.state.rows) {
+            if let Ok(mut recorder) = self.recorder.lock() {
+                if recorder.as_mut().is_some_and(|rec| rec.resize(cols, rows).is_err()) {
+                    *recorder = None;
+                }
+            }
+        }
+
         // Resize terminal state
         self.state.resize(cols, rows);
 
         // Resize PTY
         if let Some(pty) = &self.pty {
-            pty.resize_pty(cols, rows)?;
+            pty.resize(cols, rows)?;
         }
 
         Ok(())
     }
 
-    #[cfg(not(windows))]
-    pub fn resize(&mut self, cols: u16, rows: u16) -> Result<(), String> {
-        self.state.resize(cols, rows);
-        Ok(())
-    }
-
     /// Get the terminal title
     #[allow(dead_code)]
     pub fn title(&self) -> &str {
@@ -287,18 +410,17 @@ impl Drop for Session {
     fn drop(&mut self) {
         self.running.store(false, Ordering::SeqCst);
 
-        #[cfg(windows)]
-        {
-            // Cancel any pending read operations to unblock the reader thread
-            if let Some(pty) = &self.pty {
-                pty.cancel_read();
-            }
+        // Drop the channel first in case the reader is waiting for room to
+        // send: closing the PTY can block until its output is drained
+        self.output_rx = None;
+        // Hang up the PTY to unblock the reader thread
+        if let Some(pty) = &self.pty {
+            pty.close();
+        }
 
-            // Wait for reader thread to finish
-            if let Some(handle) = self.reader_thread.take() {
-                // Give it a moment to exit
-                let _ = handle.join();
-            }
+        // Wait for reader thread to finish
+        if let Some(handle) = self.reader_thread.take() {
+            let _ = handle.join();
         }
     }
 }
@@ -390,3 +512,219 @@ impl SessionManager {
         self.sessions.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pty::{wait_until, MockPty};
+
+    fn mock_session(mock: &MockPty) -> Session {
+        let mut session = Session::new(1, 20, 5);
+        session.start_with_pty(Arc::new(mock.clone()));
+        session
+    }
+
+    fn row_text(session: &Session, row: usize) -> String {
+        let screen = session.state.active_screen();
+        (0..session.state.cols as usize)
+            .filter_map(|col| screen.get_char_at(row, col))
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn test_output_reaches_screen() {
+        let mock = MockPty::with_output(b"hello\r\n");
+        mock.push_output(b"world");
+        let mut session = mock_session(&mock);
+
+        assert!(wait_until(|| {
+            let _ = session.process_output(usize::MAX);
+            row_text(&session, 1) == "world"
+        }));
+        assert_eq!(row_text(&session, 0), "hello");
+    }
+
+    #[test]
+    fn test_output_budget() {
+        let mock = MockPty::new();
+        for _ in 0..4 {
+            mock.push_output(b"aaaaaaaaaa");
+        }
+        let mut session = mock_session(&mock);
+        assert!(wait_until(|| !mock.has_pending_output()));
+
+        // Stops after the chunk that uses up the budget
+        assert!(session.process_output(15).unwrap());
+        assert_eq!(row_text(&session, 0), "a".repeat(20));
+        assert_eq!(row_text(&session, 1), "");
+
+        session.process_output(usize::MAX).unwrap();
+        assert_eq!(row_text(&session, 1), "a".repeat(20));
+    }
+
+    #[test]
+    fn test_output_channel_is_bounded() {
+        let mock = MockPty::new();
+        for _ in 0..OUTPUT_CHANNEL_CAPACITY * 2 {
+            mock.push_output(b"x");
+        }
+        let mut session = mock_session(&mock);
+
+        // The reader stops taking output once the channel is full
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(mock.has_pending_output());
+
+        assert!(wait_until(|| {
+            let _ = session.process_output(usize::MAX);
+            !mock.has_pending_output()
+        }));
+    }
+
+    #[test]
+    fn test_output_pipe_gets_raw_output() {
+        let path = std::env::temp_dir().join(format!("wtmux-session-pipe-{}.log", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mock = MockPty::new();
+        let mut session = mock_session(&mock);
+
+        let pipe = OutputPipe::open(PipeTarget::File(path.clone()), false).unwrap();
+        session.set_output_pipe(Some(pipe));
+        mock.push_output(b"\x1b[1mlog line\x1b[0m\r\n");
+        assert!(wait_until(|| {
+            let _ = session.process_output(usize::MAX);
+            row_text(&session, 0) == "log line"
+        }));
+
+        assert_eq!(session.output_pipe_target(), Some(PipeTarget::File(path.clone())));
+        drop(session.take_output_pipe());
+        assert!(wait_until(|| {
+            std::fs::read(&path).is_ok_and(|bytes| bytes == b"\x1b[1mlog line\x1b[0m\r\n")
+        }));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_feed_bytes_split_character() {
+        let mut session = Session::new(1, 20, 5);
+        let bytes = "日本".as_bytes();
+
+        // Split in the middle of the second character
+        session.feed_bytes(&bytes[..4]);
+        session.feed_bytes(&bytes[4..]);
+        assert_eq!(row_text(&session, 0).replace(' ', ""), "日本");
+    }
+
+    #[test]
+    fn test_feed_bytes_osc_title_utf8() {
+        let mut session = Session::new(1, 20, 5);
+        let bytes = "\x1b]2;ログ\x07".as_bytes();
+
+        session.feed_bytes(&bytes[..6]);
+        session.feed_bytes(&bytes[6..]);
+        assert_eq!(session.title(), "ログ");
+        assert_eq!(row_text(&session, 0), "");
+    }
+
+    #[test]
+    fn test_cp932_session() {
+        let mock = MockPty::with_output(b"\x93\xfa\x96\x7b");
+        let mut session = mock_session(&mock);
+        session.set_encoding(Encoding::Cp932);
+
+        assert!(wait_until(|| {
+            let _ = session.process_output(usize::MAX);
+            row_text(&session, 0).replace(' ', "") == "日本"
+        }));
+
+        // Typed/pasted text is converted back to Shift-JIS
+        session.write("本".as_bytes()).unwrap();
+        assert!(wait_until(|| mock.written() == b"\x96\x7b"));
+    }
+
+    #[test]
+    fn test_write_is_recorded() {
+        let mock = MockPty::new();
+        let session = mock_session(&mock);
+
+        session.write(b"ls\r").unwrap();
+        assert!(wait_until(|| mock.written() == b"ls\r"));
+    }
+
+    #[test]
+    fn test_send_response_cursor_position() {
+        // Move to row 3, column 5, then request a cursor position report
+        let mock = MockPty::with_output(b"\x1b[3;5H\x1b[6n");
+        let mut session = mock_session(&mock);
+
+        assert!(wait_until(|| {
+            let _ = session.process_output(usize::MAX);
+            !mock.written().is_empty()
+        }));
+        assert_eq!(mock.written_string(), "\x1b[3;5R");
+    }
+
+    #[test]
+    fn test_send_response_device_attributes() {
+        let mock = MockPty::with_output(b"\x1b[c");
+        let mut session = mock_session(&mock);
+
+        assert!(wait_until(|| {
+            let _ = session.process_output(usize::MAX);
+            !mock.written().is_empty()
+        }));
+        assert_eq!(mock.written(), Response::DeviceAttributes.to_bytes());
+    }
+
+    #[test]
+    fn test_exit_drains_pending_output() {
+        let mock = MockPty::with_output(b"bye");
+        mock.exit(0);
+        let mut session = mock_session(&mock);
+
+        assert!(wait_until(|| {
+            let _ = session.process_output(usize::MAX);
+            !session.is_running()
+        }));
+        let _ = session.process_output(usize::MAX);
+        assert_eq!(row_text(&session, 0), "bye");
+    }
+
+    #[test]
+    fn test_events_wake_on_output_and_exit() {
+        let (tx, rx) = mpsc::channel();
+        let mock = MockPty::new();
+        let mut session = Session::new(1, 20, 5);
+        session.set_event_sender(tx);
+        session.start_with_pty(Arc::new(mock.clone()));
+
+        let timeout = std::time::Duration::from_secs(5);
+        mock.push_output(b"a");
+        assert!(matches!(rx.recv_timeout(timeout), Ok(AppEvent::PtyOutput)));
+
+        // No further wakeup until the pending output has been processed
+        mock.push_output(b"b");
+        assert!(wait_until(|| !mock.has_pending_output()));
+        assert!(rx.try_recv().is_err());
+        session.process_output(usize::MAX).unwrap();
+
+        mock.push_output(b"c");
+        assert!(matches!(rx.recv_timeout(timeout), Ok(AppEvent::PtyOutput)));
+        session.process_output(usize::MAX).unwrap();
+
+        mock.exit(0);
+        assert!(matches!(rx.recv_timeout(timeout), Ok(AppEvent::PtyExited)));
+        assert!(!session.is_running());
+    }
+
+    #[test]
+    fn test_resize_reaches_pty() {
+        let mock = MockPty::new();
+        let mut session = mock_session(&mock);
+
+        session.resize(40, 10).unwrap();
+        assert_eq!(mock.size(), (40, 10));
+        assert_eq!((session.state.cols, session.state.rows), (40, 10));
+    }
+}
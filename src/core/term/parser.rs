@@ -2,7 +2,9 @@
 //!
 //! Parses ANSI/VT escape sequences and updates terminal state.
 
-use super::state::{AttrFlags, Color, TerminalState};
+use std::path::PathBuf;
+
+use super::state::{AttrFlags, Charset, Color, TerminalState};
 
 /// Response that needs to be sent back to the PTY
 #[derive(Debug, Clone)]
@@ -13,6 +15,15 @@ pub enum Response {
     DeviceAttributes,
     /// Secondary device attributes response
     SecondaryDeviceAttributes,
+    /// Device status report, terminal OK: ESC [ 0 n
+    StatusOk,
+    /// Mode report (DECRPM): ESC [ ? mode ; value $ y
+    ///
+    /// The state is `None` for modes that are not recognized.
+    ModeReport { private: bool, mode: u16, state: Option<bool> },
+    /// Setting report (DECRQSS): DCS 1 $ r setting ST, or DCS 0 $ r ST if
+    /// the request was not valid
+    SettingReport(Option<String>),
 }
 
 impl Response {
@@ -29,10 +40,27 @@ impl Response {
                 // VT220 response
                 b"\x1b[>1;10;0c".to_vec()
             }
+            Response::StatusOk => b"\x1b[0n".to_vec(),
+            Response::ModeReport { private, mode, state } => {
+                let value = match state {
+                    None => 0,
+                    Some(true) => 1,
+                    Some(false) => 2,
+                };
+                let marker = if *private { "?" } else { "" };
+                format!("\x1b[{}{};{}$y", marker, mode, value).into_bytes()
+            }
+            Response::SettingReport(Some(setting)) => {
+                format!("\x1bP1$r{}\x1b\\", setting).into_bytes()
+            }
+            Response::SettingReport(None) => b"\x1bP0$r\x1b\\".to_vec(),
         }
     }
 }
 
+/// Longest DCS payload kept for a handler; longer sequences are discarded
+const DCS_DATA_LIMIT: usize = 64 * 1024;
+
 /// Parser state machine
 pub struct VtParser {
     state: ParserState,
@@ -40,6 +68,12 @@ pub struct VtParser {
     intermediates: Vec<u8>,
     current_param: Option<u16>,
     osc_string: String,
+    /// Final byte of the DCS sequence being received
+    dcs_final: u8,
+    /// Payload of the DCS sequence being received
+    dcs_data: Vec<u8>,
+    /// Set once the payload has outgrown `DCS_DATA_LIMIT`
+    dcs_overflow: bool,
 }
 
 #[derive(Clone, Copy, Default, PartialEq)]
@@ -53,6 +87,30 @@ enum ParserState {
     CsiIntermediate,
     OscString,
     EscapeInOsc,  // ESC received within OSC, waiting for backslash
+    DcsEntry,
+    DcsParam,
+    DcsIntermediate,
+    DcsPassthrough,
+    DcsIgnore,
+    EscapeInDcs,  // ESC received within DCS, waiting for backslash
+}
+
+impl ParserState {
+    /// Whether the parser is inside an OSC or DCS string, where C0 controls
+    /// are not executed
+    fn in_string(self) -> bool {
+        matches!(
+            self,
+            ParserState::OscString
+                | ParserState::EscapeInOsc
+                | ParserState::DcsEntry
+                | ParserState::DcsParam
+                | ParserState::DcsIntermediate
+                | ParserState::DcsPassthrough
+                | ParserState::DcsIgnore
+                | ParserState::EscapeInDcs
+        )
+    }
 }
 
 impl Default for VtParser {
@@ -69,19 +127,25 @@ impl VtParser {
             intermediates: Vec::with_capacity(4),
             current_param: None,
             osc_string: String::new(),
+            dcs_final: 0,
+            dcs_data: Vec::new(),
+            dcs_overflow: false,
         }
     }
 
     /// Feed a single byte to the parser
     pub fn feed(&mut self, byte: u8, state: &mut TerminalState) -> Option<Response> {
-        // Handle C0 controls anywhere (except in OSC-related states)
-        if byte < 0x20 && self.state != ParserState::OscString && self.state != ParserState::EscapeInOsc {
+        // Handle C0 controls anywhere (except in OSC and DCS strings)
+        if byte < 0x20 && !self.state.in_string() {
             match byte {
                 0x1B => {
                     self.enter_escape();
                     return None;
                 }
-                0x07 => return None, // BEL - ignore
+                0x07 => {
+                    state.bell = true;
+                    return None;
+                }
                 0x08 => {
                     state.backspace();
                     return None;
@@ -90,7 +154,7 @@ impl VtParser {
                     state.horizontal_tab();
                     return None;
                 }
-                0x0A | 0x0B | 0x0C => {
+                0x0A..=0x0C => {
                     state.linefeed();
                     return None;
                 }
@@ -98,6 +162,16 @@ impl VtParser {
                     state.carriage_return();
                     return None;
                 }
+                0x0E => {
+                    // SO - Shift Out: G1 into GL
+                    state.charsets.invoke(1);
+                    return None;
+                }
+                0x0F => {
+                    // SI - Shift In: G0 into GL
+                    state.charsets.invoke(0);
+                    return None;
+                }
                 _ => return None,
             }
         }
@@ -111,7 +185,36 @@ impl VtParser {
             ParserState::CsiIntermediate => self.csi_intermediate(byte, state),
             ParserState::OscString => self.osc_string_state(byte, state),
             ParserState::EscapeInOsc => self.escape_in_osc(byte, state),
+            ParserState::DcsEntry | ParserState::DcsParam | ParserState::DcsIntermediate => {
+                self.dcs_header(byte)
+            }
+            ParserState::DcsPassthrough | ParserState::DcsIgnore => self.dcs_string(byte),
+            ParserState::EscapeInDcs => self.escape_in_dcs(byte, state),
+        }
+    }
+
+    /// Feed a decoded character to the parser
+    ///
+    /// ASCII goes through the byte state machine; other characters are
+    /// printed, or collected when they are part of an OSC string.
+    pub fn feed_char(&mut self, ch: char, state: &mut TerminalState) -> Option<Response> {
+        if ch.is_ascii() {
+            return self.feed(ch as u8, state);
+        }
+        match self.state {
+            ParserState::OscString => self.osc_string.push(ch),
+            ParserState::DcsPassthrough => {
+                let mut buf = [0; 4];
+                self.push_dcs_data(ch.encode_utf8(&mut buf).as_bytes());
+            }
+            // Not valid in a DCS header; the string is ignored
+            ParserState::DcsEntry | ParserState::DcsParam | ParserState::DcsIntermediate => {
+                self.state = ParserState::DcsIgnore;
+            }
+            ParserState::DcsIgnore => {}
+            _ => state.put_char(ch),
         }
+        None
     }
 
     /// Handle ESC received within OSC sequence
@@ -138,7 +241,7 @@ impl VtParser {
     }
 
     fn ground(&mut self, byte: u8, state: &mut TerminalState) -> Option<Response> {
-        if byte >= 0x20 && byte < 0x7F {
+        if (0x20..0x7F).contains(&byte) {
             state.put_char(byte as char);
         } else if byte >= 0x80 {
             // UTF-8 or extended ASCII - pass through for now
@@ -160,6 +263,16 @@ impl VtParser {
                 self.state = ParserState::OscString;
                 self.osc_string.clear();
             }
+            b'P' => {
+                // DCS - Device Control String
+                self.state = ParserState::DcsEntry;
+                self.params.clear();
+                self.intermediates.clear();
+                self.current_param = None;
+                self.dcs_final = 0;
+                self.dcs_data.clear();
+                self.dcs_overflow = false;
+            }
             b'7' => {
                 // DECSC - Save cursor
                 state.save_cursor();
@@ -186,6 +299,21 @@ impl VtParser {
                 state.reverse_index();
                 self.state = ParserState::Ground;
             }
+            b'H' => {
+                // HTS - Horizontal tab set
+                state.set_tab_stop();
+                self.state = ParserState::Ground;
+            }
+            b'N' | b'O' => {
+                // SS2 / SS3 - Single shift G2 / G3
+                state.charsets.single_shift(if byte == b'N' { 2 } else { 3 });
+                self.state = ParserState::Ground;
+            }
+            b'n' | b'o' => {
+                // LS2 / LS3 - Locking shift G2 / G3 into GL
+                state.charsets.invoke(if byte == b'n' { 2 } else { 3 });
+                self.state = ParserState::Ground;
+            }
             b'c' => {
                 // RIS - Full reset
                 *state = TerminalState::new(state.cols, state.rows);
@@ -203,14 +331,24 @@ impl VtParser {
         None
     }
 
-    fn escape_intermediate(&mut self, byte: u8, _state: &mut TerminalState) -> Option<Response> {
+    fn escape_intermediate(&mut self, byte: u8, state: &mut TerminalState) -> Option<Response> {
         match byte {
             0x20..=0x2F => {
                 self.intermediates.push(byte);
             }
             0x30..=0x7E => {
                 // Final byte - execute and return to ground
-                // Most of these are charset selections which we ignore for now
+                // SCS - Designate a character set into G0–G3 (ESC ( F .. ESC + F)
+                let slot = match self.intermediates.as_slice() {
+                    [b'('] => Some(0),
+                    [b')'] => Some(1),
+                    [b'*'] => Some(2),
+                    [b'+'] => Some(3),
+                    _ => None,
+                };
+                if let (Some(slot), Some(charset)) = (slot, Charset::from_designator(byte)) {
+                    state.charsets.designate(slot, charset);
+                }
                 self.state = ParserState::Ground;
             }
             _ => {
@@ -325,6 +463,140 @@ impl VtParser {
         None
     }
 
+    /// Parameters, intermediates and final byte of a DCS sequence
+    fn dcs_header(&mut self, byte: u8) -> Option<Response> {
+        match (self.state, byte) {
+            // CAN and SUB abort the sequence
+            (_, 0x18 | 0x1A) => self.state = ParserState::Ground,
+            (_, 0x1B) => self.state = ParserState::EscapeInDcs,
+            (_, 0x00..=0x1F) => {}
+            (ParserState::DcsEntry | ParserState::DcsParam, b'0'..=b'9') => {
+                let digit = (byte - b'0') as u16;
+                self.current_param = Some(
+                    self.current_param.unwrap_or(0).saturating_mul(10).saturating_add(digit)
+                );
+                self.state = ParserState::DcsParam;
+            }
+            (ParserState::DcsEntry | ParserState::DcsParam, b';') => {
+                self.params.push(self.current_param.take().unwrap_or(0));
+                self.state = ParserState::DcsParam;
+            }
+            (ParserState::DcsEntry, b'<'..=b'?') => {
+                // Private marker
+                self.intermediates.push(byte);
+                self.state = ParserState::DcsParam;
+            }
+            (_, 0x20..=0x2F) => {
+                if let Some(p) = self.current_param.take() {
+                    self.params.push(p);
+                }
+                self.intermediates.push(byte);
+                self.state = ParserState::DcsIntermediate;
+            }
+            (_, 0x40..=0x7E) => {
+                // Final byte - hook: the payload follows until ST
+                if let Some(p) = self.current_param.take() {
+                    self.params.push(p);
+                }
+                self.dcs_final = byte;
+                self.state = ParserState::DcsPassthrough;
+            }
+            // Misplaced parameter bytes make the sequence invalid
+            _ => self.state = ParserState::DcsIgnore,
+        }
+        None
+    }
+
+    /// DCS payload, up to ST
+    fn dcs_string(&mut self, byte: u8) -> Option<Response> {
+        match byte {
+            0x18 | 0x1A => self.state = ParserState::Ground,
+            0x1B => self.state = ParserState::EscapeInDcs,
+            0x7F => {}
+            _ if self.state == ParserState::DcsPassthrough => self.push_dcs_data(&[byte]),
+            _ => {}
+        }
+        None
+    }
+
+    /// Handle ESC received within a DCS sequence
+    fn escape_in_dcs(&mut self, byte: u8, state: &mut TerminalState) -> Option<Response> {
+        // The final byte is only set once the sequence has been hooked
+        let passthrough = self.dcs_final != 0;
+        match byte {
+            b'\\' => {
+                // ST (ESC \) - String Terminator: unhook
+                self.state = ParserState::Ground;
+                if self.dcs_overflow {
+                    // A handler never sees a truncated payload
+                    tracing::debug!(
+                        "DCS payload over {} bytes discarded: final={:?}",
+                        DCS_DATA_LIMIT,
+                        self.dcs_final as char
+                    );
+                    return None;
+                }
+                if passthrough {
+                    return self.execute_dcs(state);
+                }
+                None
+            }
+            0x1B if passthrough => {
+                // A doubled ESC is a literal one, as in tmux passthrough
+                self.push_dcs_data(&[0x1B]);
+                self.state = ParserState::DcsPassthrough;
+                None
+            }
+            _ => {
+                // Not ST: drop the sequence and process this byte as a new escape
+                self.enter_escape();
+                self.escape(byte, state)
+            }
+        }
+    }
+
+    fn push_dcs_data(&mut self, bytes: &[u8]) {
+        if self.dcs_overflow {
+            return;
+        }
+        if self.dcs_data.len() + bytes.len() <= DCS_DATA_LIMIT {
+            self.dcs_data.extend_from_slice(bytes);
+        } else {
+            self.dcs_overflow = true;
+            self.dcs_data = Vec::new();
+        }
+    }
+
+    /// Execute a complete DCS sequence
+    fn execute_dcs(&mut self, state: &mut TerminalState) -> Option<Response> {
+        if self.intermediates == b"$" && self.dcs_final == b'q' {
+            // DECRQSS - Request Selection or Setting
+            return Some(Response::SettingReport(self.setting_report(state)));
+        }
+        tracing::debug!(
+            "Unhandled DCS: intermediates={:?}, params={:?}, final={:?}, {} bytes",
+            self.intermediates,
+            self.params,
+            self.dcs_final as char,
+            self.dcs_data.len()
+        );
+        None
+    }
+
+    /// Current value of the setting named by a DECRQSS payload, in the form
+    /// of the sequence that sets it
+    fn setting_report(&self, state: &TerminalState) -> Option<String> {
+        match self.dcs_data.as_slice() {
+            b"m" => Some(format!("{}m", state.current_attrs.sgr_params())),
+            b"r" => {
+                let (top, bottom) = state.scroll_region;
+                Some(format!("{};{}r", top + 1, bottom + 1))
+            }
+            b" q" => Some(format!("{} q", state.active_cursor().shape.to_decscusr())),
+            _ => None,
+        }
+    }
+
     fn execute_csi(&mut self, final_byte: u8, state: &mut TerminalState) -> Option<Response> {
         let is_private = self.intermediates.contains(&b'?');
         let is_gt = self.intermediates.contains(&b'>');
@@ -364,8 +636,7 @@ impl VtParser {
             }
             (false, false, b'G') => {
                 // CHA - Cursor Character Absolute
-                let col = params.first().copied().unwrap_or(1);
-                state.active_cursor_mut().col = col.saturating_sub(1).min(state.cols - 1);
+                state.set_cursor_col(params.first().copied().unwrap_or(1));
                 None
             }
             (false, false, b'H') | (false, false, b'f') => {
@@ -375,10 +646,24 @@ impl VtParser {
                 state.cursor_position(row, col);
                 None
             }
+            (false, false, b'I') => {
+                // CHT - Cursor Forward Tabulation
+                state.forward_tab(params.first().copied().unwrap_or(1).max(1));
+                None
+            }
+            (false, false, b'Z') => {
+                // CBT - Cursor Backward Tabulation
+                state.backward_tab(params.first().copied().unwrap_or(1).max(1));
+                None
+            }
+            (false, false, b'g') => {
+                // TBC - Tab Clear
+                state.clear_tab_stops(params.first().copied().unwrap_or(0));
+                None
+            }
             (false, false, b'd') => {
                 // VPA - Line Position Absolute
-                let row = params.first().copied().unwrap_or(1);
-                state.active_cursor_mut().row = row.saturating_sub(1).min(state.rows - 1);
+                state.set_cursor_row(params.first().copied().unwrap_or(1));
                 None
             }
 
@@ -405,37 +690,12 @@ impl VtParser {
             // Character operations
             (false, false, b'@') => {
                 // ICH - Insert Characters
-                let n = params.first().copied().unwrap_or(1).max(1) as usize;
-                let cursor = state.active_cursor().clone();
-                let screen = state.active_screen_mut();
-                let row = cursor.row as usize;
-                let col = cursor.col as usize;
-
-                // Shift cells right
-                for _ in 0..n {
-                    if col < screen.rows[row].cells.len() {
-                        screen.rows[row].cells.pop();
-                        screen.rows[row].cells.insert(col, super::state::Cell::default());
-                    }
-                }
-                screen.mark_dirty(row);
+                state.insert_chars(params.first().copied().unwrap_or(1).max(1));
                 None
             }
             (false, false, b'P') => {
                 // DCH - Delete Characters
-                let n = params.first().copied().unwrap_or(1).max(1) as usize;
-                let cursor = state.active_cursor().clone();
-                let screen = state.active_screen_mut();
-                let row = cursor.row as usize;
-                let col = cursor.col as usize;
-
-                for _ in 0..n {
-                    if col < screen.rows[row].cells.len() {
-                        screen.rows[row].cells.remove(col);
-                        screen.rows[row].cells.push(super::state::Cell::default());
-                    }
-                }
-                screen.mark_dirty(row);
+                state.delete_chars(params.first().copied().unwrap_or(1).max(1));
                 None
             }
             (false, false, b'X') => {
@@ -484,7 +744,16 @@ impl VtParser {
 
             // Save/restore cursor
             (false, false, b's') => {
-                state.save_cursor();
+                if state.modes.left_right_margin_mode {
+                    // DECSLRM - Set left and right margins
+                    let left = params.first().copied().unwrap_or(1);
+                    let right = params.get(1).copied().filter(|&right| right > 0).unwrap_or(state.cols);
+                    state.set_margins(left, right);
+                    state.cursor_position(1, 1);
+                } else {
+                    // SCOSC - Save cursor
+                    state.save_cursor();
+                }
                 None
             }
             (false, false, b'u') => {
@@ -497,13 +766,12 @@ impl VtParser {
                 match params.first() {
                     Some(5) => {
                         // Status report - we're OK
-                        // Would send back CSI 0 n
-                        None
+                        Some(Response::StatusOk)
                     }
                     Some(6) => {
                         // Cursor position report
-                        let cursor = state.active_cursor();
-                        Some(Response::CursorPosition(cursor.row + 1, cursor.col + 1))
+                        let (row, col) = state.reported_cursor_position();
+                        Some(Response::CursorPosition(row, col))
                     }
                     _ => None,
                 }
@@ -531,6 +799,13 @@ impl VtParser {
                 None
             }
 
+            // DECRQM - Request Mode
+            (private, false, b'p') if self.intermediates.ends_with(b"$") => {
+                let mode = params.first().copied().unwrap_or(0);
+                let state = if private { state.private_mode(mode) } else { state.ansi_mode(mode) };
+                Some(Response::ModeReport { private, mode, state })
+            }
+
             // Standard modes
             (false, false, b'h') => {
                 for &p in params {
@@ -681,12 +956,50 @@ impl VtParser {
                     // Set title
                     state.title = text.to_string();
                 }
+                "7" => {
+                    // Current working directory: file://host/path
+                    if let Some(cwd) = parse_file_url(text) {
+                        state.cwd = Some(cwd);
+                    }
+                }
                 _ => {}
             }
         }
     }
 }
 
+/// Parse a `file://host/path` URL as sent in OSC 7
+///
+/// The host is ignored. Windows shells send paths like `/C:/Users`, which
+/// lose their leading slash.
+fn parse_file_url(url: &str) -> Option<PathBuf> {
+    let rest = url.strip_prefix("file://")?;
+    let path = &rest[rest.find('/')?..];
+
+    // Percent-decode
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut iter = path.bytes();
+    while let Some(b) = iter.next() {
+        if b == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            let hex = std::str::from_utf8(&hex).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+    let path = String::from_utf8_lossy(&bytes);
+
+    let is_drive_path = path.len() >= 3
+        && path.as_bytes()[1].is_ascii_alphabetic()
+        && path.as_bytes()[2] == b':';
+    if is_drive_path {
+        Some(PathBuf::from(&path[1..]))
+    } else {
+        Some(PathBuf::from(path.as_ref()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -705,6 +1018,208 @@ mod tests {
         assert_eq!(state.active_cursor().col, 9);
     }
 
+    #[test]
+    fn test_bell() {
+        let mut state = TerminalState::new(80, 24);
+        let mut parser = VtParser::new();
+
+        // BEL terminating an OSC is not a bell
+        for byte in b"\x1b]0;title\x07" {
+            parser.feed(*byte, &mut state);
+        }
+        assert!(!state.bell);
+
+        for byte in b"done\x07" {
+            parser.feed(*byte, &mut state);
+        }
+        assert!(state.bell);
+        assert_eq!(state.active_cursor().col, 4);
+    }
+
+    #[test]
+    fn test_dec_special_graphics() {
+        let mut state = TerminalState::new(80, 24);
+        let mut parser = VtParser::new();
+        let row = |state: &TerminalState| -> String {
+            (0..8).filter_map(|col| state.active_screen().get_char_at(0, col)).collect()
+        };
+
+        // G0 line drawing, then back to ASCII
+        for byte in b"\x1b(0lqk\x1b(Bq" {
+            parser.feed(*byte, &mut state);
+        }
+        assert_eq!(row(&state), "┌─┐q    ");
+
+        // G1 line drawing invoked with SO, saved and restored with the cursor
+        for byte in b"\r\x1b)0\x0ex\x1b7\x0f\x1b8x" {
+            parser.feed(*byte, &mut state);
+        }
+        assert_eq!(row(&state), "││┐q    ");
+
+        // UK set in G2 for a single character
+        for byte in b"\r\x0f\x1b*A\x1bN##" {
+            parser.feed(*byte, &mut state);
+        }
+        assert_eq!(row(&state), "£#┐q    ");
+    }
+
+    #[test]
+    fn test_tab_stops() {
+        let mut state = TerminalState::new(40, 24);
+        let mut parser = VtParser::new();
+        let mut feed = |bytes: &[u8], state: &mut TerminalState| {
+            for byte in bytes {
+                parser.feed(*byte, state);
+            }
+        };
+
+        feed(b"\t", &mut state);
+        assert_eq!(state.active_cursor().col, 8);
+
+        // Clear all stops, set ones at 5 and 12
+        feed(b"\x1b[3g\x1b[6G\x1bH\x1b[13G\x1bH\r", &mut state);
+        feed(b"\t", &mut state);
+        assert_eq!(state.active_cursor().col, 5);
+        feed(b"\x1b[2I", &mut state);
+        assert_eq!(state.active_cursor().col, 39);
+        feed(b"\x1b[Z", &mut state);
+        assert_eq!(state.active_cursor().col, 12);
+
+        // Clear the stop at the cursor
+        feed(b"\x1b[g\x1b[Z", &mut state);
+        assert_eq!(state.active_cursor().col, 5);
+        feed(b"\x1b[5Z", &mut state);
+        assert_eq!(state.active_cursor().col, 0);
+
+        // Stops belong to the terminal, not the screen
+        feed(b"\x1b[?1049h\r\t", &mut state);
+        assert_eq!(state.active_cursor().col, 5);
+        feed(b"\x1b[?1049l", &mut state);
+
+        // Resizing restores the default stops
+        state.resize(30, 24);
+        feed(b"\t\t", &mut state);
+        assert_eq!(state.active_cursor().col, 16);
+    }
+
+    #[test]
+    fn test_insert_mode() {
+        let mut state = TerminalState::new(8, 24);
+        let mut parser = VtParser::new();
+        let row = |state: &TerminalState| -> String {
+            (0..8).filter_map(|col| state.active_screen().get_char_at(0, col)).collect()
+        };
+
+        for ch in "abcdef".chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        // Insert before "c"; "f" is pushed off the edge
+        for ch in "\x1b[3G\x1b[4hXYZ\x1b[4l".chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        assert_eq!(row(&state), "abXYZcde");
+
+        // A wide character pushed to the edge loses its right half
+        for ch in "\r\x1b[2K\x1b[7G日\r\x1b[4hx".chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        assert_eq!(state.active_screen().get_char_at(0, 0), Some('x'));
+        assert!(!state.active_screen().rows[0].cells.iter().any(|cell| cell.width == 2));
+        assert_eq!(state.active_screen().rows[0].cells.len(), 8);
+
+        // Inserting a wide character into the middle of another
+        for ch in "\r\x1b[4l\x1b[2K日本\x1b[2G\x1b[4h語".chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        let cells = &state.active_screen().rows[0].cells;
+        assert_eq!(cells[0].c(), ' ');
+        assert_eq!(cells[1].grapheme, "語");
+        assert!(cells[2].is_continuation());
+        assert_eq!(cells[3].c(), ' ');
+        assert_eq!(cells[4].grapheme, "本");
+    }
+
+    #[test]
+    fn test_origin_mode_and_margins() {
+        let mut state = TerminalState::new(10, 12);
+        let mut parser = VtParser::new();
+        let mut feed = |bytes: &[u8], state: &mut TerminalState| {
+            bytes.iter().filter_map(|byte| parser.feed(*byte, state)).last()
+        };
+        let row = |state: &TerminalState, row: usize| -> String {
+            (0..10).filter_map(|col| state.active_screen().get_char_at(row, col)).collect()
+        };
+
+        feed(b"\x1b[6;1HX\x1b[6;8HY", &mut state);
+
+        // Origin mode addresses the cursor within the scroll region
+        feed(b"\x1b[5;10r\x1b[?6h", &mut state);
+        assert_eq!((state.active_cursor().row, state.active_cursor().col), (4, 0));
+        let report = feed(b"\x1b[20;1H\x1b[6n", &mut state);
+        assert!(matches!(report, Some(Response::CursorPosition(6, 1))));
+        assert_eq!(state.active_cursor().row, 9);
+
+        // Left/right margins: printing wraps at the right margin
+        feed(b"\x1b[?69h\x1b[3;6s", &mut state);
+        assert_eq!((state.active_cursor().row, state.active_cursor().col), (4, 2));
+        feed(b"abcdefg", &mut state);
+        assert_eq!(row(&state, 4), "  abcd    ");
+        assert_eq!(row(&state, 5), "X efg  Y  ");
+
+        // Scrolling, DCH and ICH only move the cells between the margins
+        feed(b"\x1b[S", &mut state);
+        assert_eq!(row(&state, 4), "  efg     ");
+        assert_eq!(row(&state, 5), "X      Y  ");
+        feed(b"\x1b[1;1H\x1b[P", &mut state);
+        assert_eq!(row(&state, 4), "  fg      ");
+        feed(b"\x1b[2@", &mut state);
+        assert_eq!(row(&state, 4), "    fg    ");
+
+        // Without DECLRMM, CSI s saves the cursor again
+        feed(b"\x1b[?69l\x1b[?6l\x1b[3;4s", &mut state);
+        assert_eq!(state.margins, (0, 9));
+        assert!(state.active_cursor().saved.is_some());
+    }
+
+    #[test]
+    fn test_margins_at_region_edges() {
+        let mut state = TerminalState::new(10, 6);
+        let mut parser = VtParser::new();
+        let mut feed = |bytes: &[u8], state: &mut TerminalState| {
+            for byte in bytes {
+                parser.feed(*byte, state);
+            }
+        };
+        let row = |state: &TerminalState, row: usize| -> String {
+            (0..10).filter_map(|col| state.active_screen().get_char_at(row, col)).collect()
+        };
+
+        // Scrolling or deleting the whole region blanks it
+        feed(b"abcdefghij\x1b[?69h\x1b[1;5s\x1b[6S", &mut state);
+        assert_eq!(row(&state, 0), "     fghij");
+        feed(b"\x1b[?69l\x1b[1;1Habcde\x1b[?69h\x1b[1;5s\x1b[1;1H\x1b[9M", &mut state);
+        assert_eq!(row(&state, 0), "     fghij");
+
+        // The column right of the margin is an ordinary column
+        feed(b"\x1b[3;6HZ", &mut state);
+        assert_eq!(state.active_screen().get_char_at(2, 5), Some('Z'));
+        assert_eq!(state.active_cursor().row, 2);
+
+        // Filling the right margin wraps to the left margin
+        feed(b"\x1b[4;2Hwxyzq", &mut state);
+        assert_eq!(row(&state, 3), " wxyz     ");
+        assert_eq!(row(&state, 4), "q         ");
+        feed(b"\x1b[5;1Habcde\x1b[5;6Hf", &mut state);
+        assert_eq!(row(&state, 4), "abcdef    ");
+        assert_eq!(state.active_cursor().row, 4);
+
+        // Vertical movement cancels the wrap and stays on the margin
+        feed(b"\x1b[2;1Hvwxyz\x1b[AV\x1b[3dW", &mut state);
+        assert_eq!(row(&state, 0), "    Vfghij");
+        assert_eq!(row(&state, 1), "vwxyz     ");
+        assert_eq!(state.active_screen().get_char_at(2, 4), Some('W'));
+    }
+
     #[test]
     fn test_sgr_colors() {
         let mut state = TerminalState::new(80, 24);
@@ -717,4 +1232,96 @@ mod tests {
 
         assert_eq!(state.current_attrs.fg, Color::Indexed(1));
     }
+
+    #[test]
+    fn test_osc7_working_directory() {
+        let mut state = TerminalState::new(80, 24);
+        let mut parser = VtParser::new();
+
+        for byte in b"\x1b]7;file://myhost/home/user/my%20dir\x07" {
+            parser.feed(*byte, &mut state);
+        }
+        assert_eq!(state.cwd, Some(PathBuf::from("/home/user/my dir")));
+
+        // Windows shells send a drive letter after the slash
+        for byte in b"\x1b]7;file://localhost/C:/Users/me\x1b\\" {
+            parser.feed(*byte, &mut state);
+        }
+        assert_eq!(state.cwd, Some(PathBuf::from("C:/Users/me")));
+
+        // Anything else leaves it unchanged
+        for byte in b"\x1b]7;http://example.com/\x07" {
+            parser.feed(*byte, &mut state);
+        }
+        assert_eq!(state.cwd, Some(PathBuf::from("C:/Users/me")));
+    }
+
+    #[test]
+    fn test_dcs_is_not_printed() {
+        let mut state = TerminalState::new(20, 4);
+        let mut parser = VtParser::new();
+        let row = |state: &TerminalState, row: usize| -> String {
+            (0..20).filter_map(|col| state.active_screen().get_char_at(row, col)).collect()
+        };
+
+        // DECRQSS, a Sixel image with a non-ASCII byte, tmux passthrough with
+        // a doubled ESC, and an invalid header are all swallowed
+        let input = "a\x1bP$qm\x1b\\b\x1bPq#0;2;0;0;0é~-\x1b\\c\x1bPtmux;\x1b\x1b[1m\x1b\\d";
+        for ch in input.chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        assert_eq!(parser.dcs_final, b't');
+        assert_eq!(parser.dcs_data, b"mux;\x1b[1m");
+        for ch in "\x1bP1:x\x1b\\e".chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        assert_eq!(row(&state, 0).trim_end(), "abcde");
+        assert_eq!(state.active_cursor().col, 5);
+
+        // Controls inside the string are not executed, and CAN aborts it
+        for ch in "\x1bPq\r\n\x08\x18f".chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        assert_eq!(row(&state, 0).trim_end(), "abcdef");
+        assert_eq!(state.active_cursor().row, 0);
+
+        // A new escape sequence interrupts an unterminated string
+        for ch in "\x1bPqjunk\x1b[2Gg".chars() {
+            parser.feed_char(ch, &mut state);
+        }
+        assert_eq!(row(&state, 0).trim_end(), "agcdef");
+    }
+
+    #[test]
+    fn test_mode_and_setting_reports() {
+        let mut state = TerminalState::new(80, 24);
+        let mut parser = VtParser::new();
+        let mut query = |bytes: &[u8], state: &mut TerminalState| -> String {
+            let response = bytes.iter().filter_map(|byte| parser.feed(*byte, state)).last();
+            String::from_utf8(response.map(|r| r.to_bytes()).unwrap_or_default()).unwrap()
+        };
+
+        assert_eq!(query(b"\x1b[5n", &mut state), "\x1b[0n");
+
+        // DECRQM: set, reset and not recognized
+        assert_eq!(query(b"\x1b[?2004h\x1b[?2004$p", &mut state), "\x1b[?2004;1$y");
+        assert_eq!(query(b"\x1b[?1049$p", &mut state), "\x1b[?1049;2$y");
+        assert_eq!(query(b"\x1b[?2026$p", &mut state), "\x1b[?2026;0$y");
+        assert_eq!(query(b"\x1b[4h\x1b[4$p", &mut state), "\x1b[4;1$y");
+
+        // DECRQSS for SGR, DECSTBM and DECSCUSR
+        let sgr = query(b"\x1b[1;4;91;48;5;200m\x1bP$qm\x1b\\", &mut state);
+        assert_eq!(sgr, "\x1bP1$r0;1;4;91;48;5;200m\x1b\\");
+        assert_eq!(query(b"\x1b[0;38;2;1;2;3m\x1bP$qm\x1b\\", &mut state), "\x1bP1$r0;38;2;1;2;3m\x1b\\");
+        assert_eq!(query(b"\x1b[3;20r\x1bP$qr\x1b\\", &mut state), "\x1bP1$r3;20r\x1b\\");
+        assert_eq!(query(b"\x1b[6 q\x1bP$q q\x1b\\", &mut state), "\x1bP1$r6 q\x1b\\");
+        assert_eq!(query(b"\x1bP$qx\x1b\\", &mut state), "\x1bP0$r\x1b\\");
+
+        // A payload too long to keep is not handed to the handler at all
+        let mut long = b"\x1bP$q".to_vec();
+        long.resize(long.len() + DCS_DATA_LIMIT, b' ');
+        long.extend_from_slice(b"m\x1b\\");
+        assert_eq!(query(&long, &mut state), "");
+        assert_eq!(query(b"\x1bP$qm\x1b\\", &mut state), "\x1bP1$r0;38;2;1;2;3m\x1b\\");
+    }
 }
@@ -4,6 +4,7 @@
 
 use bitflags::bitflags;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use unicode_width::UnicodeWidthChar;
 
 /// Terminal state holding all screen data
@@ -18,8 +19,18 @@ pub struct TerminalState {
     pub current_attrs: CellAttrs,
     pub modes: TerminalModes,
     pub title: String,
+    /// Working directory reported by the shell (OSC 7)
+    pub cwd: Option<PathBuf>,
+    /// Set by BEL until the owner takes it
+    pub bell: bool,
+    /// Designated and invoked character sets
+    pub charsets: CharsetState,
     /// Scroll region (top, bottom) - 0-indexed, inclusive
     pub scroll_region: (u16, u16),
+    /// Left and right margins (DECSLRM) - 0-indexed, inclusive
+    pub margins: (u16, u16),
+    /// Whether each column has a tab stop, shared by both screens
+    pub tab_stops: Vec<bool>,
     /// Text selection state
     pub selection: Option<Selection>,
 }
@@ -47,8 +58,13 @@ impl TerminalState {
             alternate_cursor: CursorState::default(),
             current_attrs: CellAttrs::default(),
             modes: TerminalModes::default(),
-            title: String::from("RustTerm"),
+            title: String::new(),
+            cwd: None,
+            bell: false,
+            charsets: CharsetState::default(),
             scroll_region: (0, rows.saturating_sub(1)),
+            margins: (0, cols.saturating_sub(1)),
+            tab_stops: default_tab_stops(cols),
             selection: None,
         }
     }
@@ -92,19 +108,41 @@ impl TerminalState {
         self.primary_screen.resize(cols, rows);
         self.alternate_screen.resize(cols, rows);
         self.scroll_region = (0, rows.saturating_sub(1));
+        self.margins = (0, cols.saturating_sub(1));
+        // A change of width resets the tab stops
+        if cols as usize != self.tab_stops.len() {
+            self.tab_stops = default_tab_stops(cols);
+        }
 
         // Clamp cursor positions
         let max_col = cols.saturating_sub(1);
         let max_row = rows.saturating_sub(1);
         
-        self.primary_cursor.col = self.primary_cursor.col.min(max_col);
-        self.primary_cursor.row = self.primary_cursor.row.min(max_row);
-        self.alternate_cursor.col = self.alternate_cursor.col.min(max_col);
-        self.alternate_cursor.row = self.alternate_cursor.row.min(max_row);
+        for cursor in [&mut self.primary_cursor, &mut self.alternate_cursor] {
+            cursor.col = cursor.col.min(max_col);
+            cursor.row = cursor.row.min(max_row);
+            cursor.wrap_pending = false;
+        }
+    }
+
+    /// Take the primary screen's history: its scrollback and the rows up to the cursor
+    ///
+    /// Those rows are left blank on the screen.
+    pub fn take_history(&mut self) -> Vec<Row> {
+        let cols = self.cols;
+        let screen = &mut self.primary_screen;
+        let used = (self.primary_cursor.row as usize + 1).min(screen.rows.len());
+
+        let mut history = std::mem::take(&mut screen.scrollback);
+        history.extend(screen.rows.drain(..used));
+        screen.rows.splice(0..0, (0..used).map(|_| Row::new(cols)));
+        screen.mark_all_dirty();
+        history
     }
 
     /// Put a character at the current cursor position
     pub fn put_char(&mut self, ch: char) {
+        let ch = self.charsets.translate(ch);
         let width = ch.width().unwrap_or(0) as u16;
 
         if width == 0 {
@@ -114,25 +152,34 @@ impl TerminalState {
         }
 
         // Get cursor position first
-        let (cursor_row, cursor_col) = {
+        let (cursor_row, cursor_col, wrap_pending) = {
             let cursor = self.active_cursor();
-            (cursor.row, cursor.col)
+            (cursor.row, cursor.col, cursor.wrap_pending)
         };
 
-        // Handle line wrap - only when cursor is completely beyond the screen edge
-        // We allow writing at cols-1 even for wide characters, trusting ConPTY to handle wrapping
-        // This prevents premature wrapping when unicode-width differs from ConPTY's calculation
-        if cursor_col >= self.cols {
+        // Handle line wrap - only when cursor is completely beyond the right margin
+        // or screen edge. We allow writing at the last column even for wide characters,
+        // trusting ConPTY to handle wrapping. This prevents premature wrapping when
+        // unicode-width differs from ConPTY's calculation
+        let (left, right) = self.margins;
+        let wrap = if wrap_pending && cursor_col == right + 1 {
+            Some((left, right))
+        } else if cursor_col >= self.cols {
+            Some((0, self.cols.saturating_sub(1)))
+        } else {
+            None
+        };
+        if let Some((wrap_col, last_col)) = wrap {
             if self.modes.auto_wrap {
                 {
                     let screen = self.active_screen_mut();
                     screen.rows[cursor_row as usize].wrapped = true;
                 }
-                self.active_cursor_mut().col = 0;
+                self.active_cursor_mut().col = wrap_col;
                 self.linefeed();
             } else {
                 // No wrap - clamp to last position
-                self.active_cursor_mut().col = self.cols.saturating_sub(1);
+                self.active_cursor_mut().col = last_col;
             }
         }
 
@@ -147,6 +194,11 @@ impl TerminalState {
             return;
         }
 
+        // In insert mode (IRM) the rest of the row moves right instead
+        if self.modes.insert_mode {
+            self.insert_chars(width);
+        }
+
         // Handle overwriting wide characters
         self.handle_wide_char_overwrite(row, col);
 
@@ -171,7 +223,75 @@ impl TerminalState {
         screen.mark_dirty(row);
 
         // Move cursor by character width
-        self.active_cursor_mut().col += width;
+        let cursor = self.active_cursor_mut();
+        cursor.col += width;
+        cursor.wrap_pending = col <= right as usize && cursor.col > right;
+    }
+
+    /// Insert `n` blank cells at the cursor, moving the rest of the row
+    /// up to the right margin right and dropping cells pushed past it (ICH)
+    pub fn insert_chars(&mut self, n: u16) {
+        let Some((row, col, right)) = self.cursor_in_margins() else {
+            return;
+        };
+        let n = (n as usize).min(right + 1 - col);
+        let cols = self.cols as usize;
+        let screen = self.active_screen_mut();
+        let cells = &mut screen.rows[row].cells;
+
+        // Wide characters split by the insertion or by the margin lose both halves
+        if col > 0 && cells[col].is_continuation() {
+            cells[col - 1] = Cell::default();
+            cells[col] = Cell::default();
+        }
+        if right + 1 < cols && cells[right + 1].is_continuation() {
+            cells[right] = Cell::default();
+            cells[right + 1] = Cell::default();
+        }
+        cells[col..=right].rotate_right(n);
+        cells[col..col + n].fill(Cell::default());
+        // One whose right half was pushed past the margin
+        if cells[right].width == 2 {
+            cells[right] = Cell::default();
+        }
+        screen.mark_dirty(row);
+    }
+
+    /// Delete `n` cells at the cursor, moving the rest of the row up to the
+    /// right margin left and blanking the cells uncovered (DCH)
+    pub fn delete_chars(&mut self, n: u16) {
+        let Some((row, col, right)) = self.cursor_in_margins() else {
+            return;
+        };
+        let n = (n as usize).min(right + 1 - col);
+        let cols = self.cols as usize;
+        let screen = self.active_screen_mut();
+        let cells = &mut screen.rows[row].cells;
+
+        if col > 0 && cells[col].is_continuation() {
+            cells[col - 1] = Cell::default();
+        }
+        if right + 1 < cols && cells[right + 1].is_continuation() {
+            cells[right] = Cell::default();
+            cells[right + 1] = Cell::default();
+        }
+        cells[col..=right].rotate_left(n);
+        cells[right + 1 - n..=right].fill(Cell::default());
+        // The right half of a wide character whose left half was deleted
+        if cells[col].is_continuation() {
+            cells[col] = Cell::default();
+        }
+        screen.mark_dirty(row);
+    }
+
+    /// Cursor row and column with the right margin, if the cursor is
+    /// between the left and right margins
+    fn cursor_in_margins(&self) -> Option<(usize, usize, usize)> {
+        let cursor = self.active_cursor();
+        let (left, right) = self.margins;
+        (left..=right)
+            .contains(&cursor.col)
+            .then_some((cursor.row as usize, cursor.col as usize, right as usize))
     }
 
     fn append_to_previous_cell(&mut self, ch: char) {
@@ -211,21 +331,33 @@ impl TerminalState {
         }
     }
 
-    /// Carriage return - move cursor to column 0
+    /// Carriage return - move cursor to the left margin, or column 0 if
+    /// left of it
     pub fn carriage_return(&mut self) {
         let row = self.active_cursor().row as usize;
-        self.active_cursor_mut().col = 0;
+        let left = self.margins.0;
+        let origin_mode = self.modes.origin_mode;
+        let cursor = self.active_cursor_mut();
+        cursor.col = if origin_mode || cursor.col >= left { left } else { 0 };
+        cursor.wrap_pending = false;
         // Mark the line dirty since content may be overwritten
         self.active_screen_mut().mark_dirty(row);
     }
 
     /// Line feed - move cursor down, scroll if needed
     pub fn linefeed(&mut self) {
-        let cursor_row = self.active_cursor().row;
+        let (cursor_row, cursor_col) = {
+            let cursor = self.active_cursor();
+            (cursor.row, cursor.col)
+        };
         let scroll_bottom = self.scroll_region.1;
         let rows = self.rows;
+        // The column past the right margin is where printing waits to wrap
+        let (left, right) = self.margins;
+        let wrap_pending = self.active_cursor().wrap_pending;
+        let in_margins = (left..=right).contains(&cursor_col) || (wrap_pending && cursor_col == right + 1);
 
-        if cursor_row >= scroll_bottom {
+        if cursor_row == scroll_bottom && in_margins {
             // At bottom of scroll region - scroll up
             self.scroll_up(1);
         } else if cursor_row < rows - 1 {
@@ -235,6 +367,7 @@ impl TerminalState {
 
     /// Backspace - move cursor left
     pub fn backspace(&mut self) {
+        self.cancel_wrap();
         let cursor = self.active_cursor_mut();
         if cursor.col > 0 {
             cursor.col -= 1;
@@ -243,85 +376,224 @@ impl TerminalState {
 
     /// Horizontal tab
     pub fn horizontal_tab(&mut self) {
-        let cols = self.cols;
+        self.forward_tab(1);
+    }
+
+    /// Move to the `n`th next tab stop, or the right margin (CHT)
+    pub fn forward_tab(&mut self, n: u16) {
+        let mut col = self.active_cursor().col;
+        let right = self.margins.1;
+        let last = if col <= right { right } else { self.cols.saturating_sub(1) };
+        for _ in 0..n {
+            let stops = &self.tab_stops;
+            col = (col + 1..last).find(|&c| stops[c as usize]).unwrap_or(last);
+        }
+        let cursor = self.active_cursor_mut();
+        cursor.col = col.min(last);
+        cursor.wrap_pending = false;
+    }
+
+    /// Move to the `n`th previous tab stop, or the left margin (CBT)
+    pub fn backward_tab(&mut self, n: u16) {
+        let mut col = self.active_cursor().col.min(self.cols.saturating_sub(1));
+        let left = self.margins.0;
+        let first = if col >= left { left } else { 0 };
+        for _ in 0..n {
+            let stops = &self.tab_stops;
+            col = (first + 1..col).rev().find(|&c| stops[c as usize]).unwrap_or(first);
+        }
         let cursor = self.active_cursor_mut();
-        // Move to next tab stop (every 8 columns)
-        cursor.col = ((cursor.col / 8) + 1) * 8;
-        if cursor.col >= cols {
-            cursor.col = cols.saturating_sub(1);
+        cursor.col = col;
+        cursor.wrap_pending = false;
+    }
+
+    /// Set a tab stop at the cursor column (HTS)
+    pub fn set_tab_stop(&mut self) {
+        let col = self.active_cursor().col as usize;
+        if let Some(stop) = self.tab_stops.get_mut(col) {
+            *stop = true;
+        }
+    }
+
+    /// Clear the tab stop at the cursor column (mode 0) or all of them
+    /// (mode 3) (TBC)
+    pub fn clear_tab_stops(&mut self, mode: u16) {
+        let col = self.active_cursor().col as usize;
+        let stops = &mut self.tab_stops;
+        match mode {
+            0 => {
+                if let Some(stop) = stops.get_mut(col) {
+                    *stop = false;
+                }
+            }
+            3 => stops.fill(false),
+            _ => {}
         }
     }
 
-    /// Scroll the screen up by n lines
+    /// Scroll the scroll region up by n lines
     pub fn scroll_up(&mut self, n: u16) {
         let (top, bottom) = self.scroll_region;
-        let cols = self.cols;
         let is_primary = !self.using_alternate;
+        let removed = self.shift_rows(top, bottom, n, true);
 
-        let screen = self.active_screen_mut();
-
-        for _ in 0..n {
-            if (top as usize) < screen.rows.len() && (bottom as usize) < screen.rows.len() {
-                let removed_row = screen.rows.remove(top as usize);
-                // Save to scrollback only for primary screen and when scrolling from top
-                if is_primary && top == 0 {
-                    screen.push_to_scrollback(removed_row);
-                }
-                screen.rows.insert(bottom as usize, Row::new(cols));
+        // Save to scrollback only for primary screen and when scrolling from top
+        if is_primary && top == 0 {
+            let screen = self.active_screen_mut();
+            for row in removed {
+                screen.push_to_scrollback(row);
             }
         }
-        screen.mark_all_dirty();
     }
 
-    /// Scroll the screen down by n lines
+    /// Scroll the scroll region down by n lines
     pub fn scroll_down(&mut self, n: u16) {
         let (top, bottom) = self.scroll_region;
+        self.shift_rows(top, bottom, n, false);
+    }
+
+    /// Move rows `top..=bottom` up or down by `n`, blanking the rows
+    /// uncovered
+    ///
+    /// Only the cells between the left and right margins move. Returns the
+    /// rows moved off the top when whole rows moved up.
+    fn shift_rows(&mut self, top: u16, bottom: u16, n: u16, up: bool) -> Vec<Row> {
         let cols = self.cols;
+        let full_width = self.margins == (0, cols.saturating_sub(1));
+        let (left, right) = (self.margins.0 as usize, self.margins.1 as usize);
+        let (top, bottom) = (top as usize, bottom as usize);
 
         let screen = self.active_screen_mut();
-
-        for _ in 0..n {
-            if (bottom as usize) < screen.rows.len() && (top as usize) <= screen.rows.len() {
-                screen.rows.remove(bottom as usize);
-                screen.rows.insert(top as usize, Row::new(cols));
+        if top > bottom || bottom >= screen.rows.len() {
+            return Vec::new();
+        }
+        let n = (n as usize).min(bottom + 1 - top);
+        let blank = || Row::new(cols);
+
+        let mut removed = Vec::new();
+        if full_width && up {
+            removed = screen.rows.drain(top..top + n).collect();
+            screen.rows.splice(bottom + 1 - n..bottom + 1 - n, (0..n).map(|_| blank()));
+        } else if full_width {
+            screen.rows.drain(bottom + 1 - n..=bottom);
+            screen.rows.splice(top..top, (0..n).map(|_| blank()));
+        } else {
+            // Nothing is left to move when the whole region is shifted out
+            if n <= bottom - top {
+                let moved: Vec<usize> = if up {
+                    (top..=bottom - n).collect()
+                } else {
+                    (top + n..=bottom).rev().collect()
+                };
+                for row in moved {
+                    let from = if up { row + n } else { row - n };
+                    let cells = screen.rows[from].cells[left..=right].to_vec();
+                    screen.rows[row].cells[left..=right].clone_from_slice(&cells);
+                }
+            }
+            let uncovered = if up { bottom + 1 - n..=bottom } else { top..=top + n - 1 };
+            for row in uncovered {
+                screen.rows[row].cells[left..=right].fill(Cell::default());
             }
         }
         screen.mark_all_dirty();
+        removed
     }
 
-    /// Cursor up
+    /// Drop a pending wrap, moving the cursor back onto the right margin
+    /// it filled
+    fn cancel_wrap(&mut self) {
+        let right = self.margins.1;
+        let cursor = self.active_cursor_mut();
+        if cursor.wrap_pending {
+            cursor.col = cursor.col.min(right);
+            cursor.wrap_pending = false;
+        }
+    }
+
+    /// Cursor up, stopping at the top margin
     pub fn cursor_up(&mut self, n: u16) {
+        self.cancel_wrap();
+        let top = self.scroll_region.0;
         let cursor = self.active_cursor_mut();
-        cursor.row = cursor.row.saturating_sub(n);
+        let limit = if cursor.row >= top { top } else { 0 };
+        cursor.row = cursor.row.saturating_sub(n).max(limit);
     }
 
-    /// Cursor down
+    /// Cursor down, stopping at the bottom margin
     pub fn cursor_down(&mut self, n: u16) {
+        self.cancel_wrap();
         let rows = self.rows;
+        let bottom = self.scroll_region.1;
         let cursor = self.active_cursor_mut();
-        cursor.row = (cursor.row + n).min(rows.saturating_sub(1));
+        let limit = if cursor.row <= bottom { bottom } else { rows.saturating_sub(1) };
+        cursor.row = cursor.row.saturating_add(n).min(limit);
     }
 
-    /// Cursor forward (right)
+    /// Cursor forward (right), stopping at the right margin
     pub fn cursor_forward(&mut self, n: u16) {
+        self.cancel_wrap();
         let cols = self.cols;
+        let right = self.margins.1;
         let cursor = self.active_cursor_mut();
-        cursor.col = (cursor.col + n).min(cols.saturating_sub(1));
+        let limit = if cursor.col <= right { right } else { cols.saturating_sub(1) };
+        cursor.col = cursor.col.saturating_add(n).min(limit);
     }
 
-    /// Cursor backward (left)
+    /// Cursor backward (left), stopping at the left margin
     pub fn cursor_backward(&mut self, n: u16) {
+        self.cancel_wrap();
+        let left = self.margins.0;
         let cursor = self.active_cursor_mut();
-        cursor.col = cursor.col.saturating_sub(n);
+        let limit = if cursor.col >= left { left } else { 0 };
+        cursor.col = cursor.col.saturating_sub(n).max(limit);
     }
 
-    /// Set cursor position (1-indexed parameters)
+    /// Set cursor position (1-indexed parameters), relative to the scroll
+    /// region and margins in origin mode
     pub fn cursor_position(&mut self, row: u16, col: u16) {
-        let rows = self.rows;
-        let cols = self.cols;
+        self.set_cursor_row(row);
+        self.set_cursor_col(col);
+    }
+
+    /// Set the cursor row (1-indexed), relative to the scroll region in
+    /// origin mode
+    pub fn set_cursor_row(&mut self, row: u16) {
+        self.cancel_wrap();
+        let (top, bottom) = if self.modes.origin_mode {
+            self.scroll_region
+        } else {
+            (0, self.rows.saturating_sub(1))
+        };
+        self.active_cursor_mut().row = top.saturating_add(row.saturating_sub(1)).min(bottom);
+    }
+
+    /// Set the cursor column (1-indexed), relative to the margins in
+    /// origin mode
+    pub fn set_cursor_col(&mut self, col: u16) {
+        let (left, right) = if self.modes.origin_mode {
+            self.margins
+        } else {
+            (0, self.cols.saturating_sub(1))
+        };
         let cursor = self.active_cursor_mut();
-        cursor.row = row.saturating_sub(1).min(rows.saturating_sub(1));
-        cursor.col = col.saturating_sub(1).min(cols.saturating_sub(1));
+        cursor.col = left.saturating_add(col.saturating_sub(1)).min(right);
+        cursor.wrap_pending = false;
+    }
+
+    /// Cursor position (1-indexed) as reported to programs, relative to
+    /// the scroll region and margins in origin mode
+    pub fn reported_cursor_position(&self) -> (u16, u16) {
+        let cursor = self.active_cursor();
+        let col = cursor.col.min(self.cols.saturating_sub(1));
+        if self.modes.origin_mode {
+            (
+                cursor.row.saturating_sub(self.scroll_region.0) + 1,
+                col.saturating_sub(self.margins.0) + 1,
+            )
+        } else {
+            (cursor.row + 1, col + 1)
+        }
     }
 
     /// Erase in display
@@ -414,39 +686,32 @@ impl TerminalState {
         screen.mark_dirty(row);
     }
 
-    /// Insert lines at cursor position
+    /// Insert lines at the cursor, within the scroll region and margins
     pub fn insert_lines(&mut self, n: u16) {
-        let cursor_row = self.active_cursor().row as usize;
-        let total_rows = self.rows as usize;
-        let cols = self.cols;
-
-        let screen = self.active_screen_mut();
-
-        for _ in 0..n {
-            if cursor_row < screen.rows.len() {
-                screen.rows.insert(cursor_row, Row::new(cols));
-                if screen.rows.len() > total_rows {
-                    screen.rows.pop();
-                }
-            }
+        let row = self.active_cursor().row;
+        let (top, bottom) = self.scroll_region;
+        if row < top || row > bottom || self.cursor_in_margins().is_none() {
+            return;
         }
-        screen.mark_all_dirty();
+        self.shift_rows(row, bottom, n, false);
+        let left = self.margins.0;
+        let cursor = self.active_cursor_mut();
+        cursor.col = left;
+        cursor.wrap_pending = false;
     }
 
-    /// Delete lines at cursor position
+    /// Delete lines at the cursor, within the scroll region and margins
     pub fn delete_lines(&mut self, n: u16) {
-        let cursor_row = self.active_cursor().row as usize;
-        let cols = self.cols;
-
-        let screen = self.active_screen_mut();
-
-        for _ in 0..n {
-            if cursor_row < screen.rows.len() {
-                screen.rows.remove(cursor_row);
-                screen.rows.push(Row::new(cols));
-            }
+        let row = self.active_cursor().row;
+        let (top, bottom) = self.scroll_region;
+        if row < top || row > bottom || self.cursor_in_margins().is_none() {
+            return;
         }
-        screen.mark_all_dirty();
+        self.shift_rows(row, bottom, n, true);
+        let left = self.margins.0;
+        let cursor = self.active_cursor_mut();
+        cursor.col = left;
+        cursor.wrap_pending = false;
     }
 
     /// Set scroll region
@@ -459,14 +724,30 @@ impl TerminalState {
         }
     }
 
+    /// Set left and right margins (1-indexed), if left/right margin mode
+    /// is enabled (DECSLRM)
+    pub fn set_margins(&mut self, left: u16, right: u16) {
+        if !self.modes.left_right_margin_mode {
+            return;
+        }
+        let cols = self.cols;
+        let left = left.saturating_sub(1).min(cols.saturating_sub(1));
+        let right = right.saturating_sub(1).min(cols.saturating_sub(1));
+        if left < right {
+            self.margins = (left, right);
+        }
+    }
+
     /// Save cursor position
     pub fn save_cursor(&mut self) {
-        let (col, row) = {
+        let (col, row, wrap_pending) = {
             let cursor = self.active_cursor();
-            (cursor.col, cursor.row)
+            (cursor.col, cursor.row, cursor.wrap_pending)
         };
         let attrs = self.current_attrs.clone();
-        let saved = SavedCursor { col, row, attrs };
+        let charsets = self.charsets.clone();
+        let origin_mode = self.modes.origin_mode;
+        let saved = SavedCursor { col, row, attrs, charsets, origin_mode, wrap_pending };
         self.active_cursor_mut().saved = Some(saved);
     }
 
@@ -477,7 +758,10 @@ impl TerminalState {
             let cursor = self.active_cursor_mut();
             cursor.col = saved.col;
             cursor.row = saved.row;
+            cursor.wrap_pending = saved.wrap_pending;
             self.current_attrs = saved.attrs;
+            self.charsets = saved.charsets;
+            self.modes.origin_mode = saved.origin_mode;
         }
     }
 
@@ -485,8 +769,20 @@ impl TerminalState {
     pub fn set_private_mode(&mut self, mode: u16, enable: bool) {
         match mode {
             1 => self.modes.application_cursor = enable,
+            6 => {
+                // DECOM - the cursor goes to the new home position
+                self.modes.origin_mode = enable;
+                self.cursor_position(1, 1);
+            }
             7 => self.modes.auto_wrap = enable,
             25 => self.active_cursor_mut().visible = enable,
+            69 => {
+                // DECLRMM - margins only apply while enabled
+                self.modes.left_right_margin_mode = enable;
+                if !enable {
+                    self.margins = (0, self.cols.saturating_sub(1));
+                }
+            }
             47 | 1047 => {
                 if enable {
                     self.using_alternate = true;
@@ -528,12 +824,41 @@ impl TerminalState {
         }
     }
 
+    /// State of a DEC private mode for DECRQM, or `None` if it is not
+    /// supported
+    pub fn private_mode(&self, mode: u16) -> Option<bool> {
+        Some(match mode {
+            1 => self.modes.application_cursor,
+            6 => self.modes.origin_mode,
+            7 => self.modes.auto_wrap,
+            25 => self.active_cursor().visible,
+            69 => self.modes.left_right_margin_mode,
+            47 | 1047 | 1049 => self.using_alternate,
+            2004 => self.modes.bracketed_paste,
+            1000 => self.modes.mouse_tracking,
+            1002 => self.modes.mouse_button_tracking,
+            1003 => self.modes.mouse_any_event,
+            1006 => self.modes.mouse_sgr_mode,
+            1015 => self.modes.mouse_urxvt_mode,
+            _ => return None,
+        })
+    }
+
+    /// State of an ANSI mode for DECRQM, or `None` if it is not supported
+    pub fn ansi_mode(&self, mode: u16) -> Option<bool> {
+        match mode {
+            4 => Some(self.modes.insert_mode),
+            20 => Some(self.modes.linefeed_newline),
+            _ => None,
+        }
+    }
+
     /// Reverse index - cursor up, scroll if at top
     pub fn reverse_index(&mut self) {
         let cursor_row = self.active_cursor().row;
         let scroll_top = self.scroll_region.0;
 
-        if cursor_row == scroll_top {
+        if cursor_row == scroll_top && self.cursor_in_margins().is_some() {
             self.scroll_down(1);
         } else {
             self.cursor_up(1);
@@ -708,6 +1033,7 @@ impl ScreenBuffer {
         }
     }
 
+    /// Resize the buffer
     pub fn resize(&mut self, new_cols: u16, new_rows: u16) {
         while self.rows.len() < new_rows as usize {
             self.rows.push(Row::new(new_cols));
@@ -928,10 +1254,40 @@ impl CellAttrs {
     pub fn reset(&mut self) {
         *self = Self::default();
     }
+
+    /// SGR parameters that recreate these attributes, starting with a reset
+    pub fn sgr_params(&self) -> String {
+        const FLAGS: [(AttrFlags, u8); 8] = [
+            (AttrFlags::BOLD, 1),
+            (AttrFlags::DIM, 2),
+            (AttrFlags::ITALIC, 3),
+            (AttrFlags::UNDERLINE, 4),
+            (AttrFlags::BLINK, 5),
+            (AttrFlags::INVERSE, 7),
+            (AttrFlags::HIDDEN, 8),
+            (AttrFlags::STRIKETHROUGH, 9),
+        ];
+        let mut params = vec!["0".to_string()];
+        for (flag, param) in FLAGS {
+            if self.flags.contains(flag) {
+                params.push(param.to_string());
+            }
+        }
+        for (color, base) in [(self.fg, 30), (self.bg, 40)] {
+            match color {
+                Color::Default => {}
+                Color::Indexed(n @ 0..=7) => params.push((base + n as u16).to_string()),
+                Color::Indexed(n @ 8..=15) => params.push((base + 60 + n as u16 - 8).to_string()),
+                Color::Indexed(n) => params.push(format!("{};5;{}", base + 8, n)),
+                Color::Rgb(r, g, b) => params.push(format!("{};2;{};{};{}", base + 8, r, g, b)),
+            }
+        }
+        params.join(";")
+    }
 }
 
 /// Color definition
-#[derive(Clone, Copy, PartialEq, Default)]
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
 pub enum Color {
     #[default]
     Default,
@@ -941,15 +1297,11 @@ pub enum Color {
 
 impl Color {
     /// Convert to crossterm color
-    pub fn to_crossterm(&self, _is_fg: bool) -> crossterm::style::Color {
+    pub fn to_crossterm(self, _is_fg: bool) -> crossterm::style::Color {
         match self {
             Color::Default => crossterm::style::Color::Reset,
-            Color::Indexed(n) => crossterm::style::Color::AnsiValue(*n),
-            Color::Rgb(r, g, b) => crossterm::style::Color::Rgb {
-                r: *r,
-                g: *g,
-                b: *b,
-            },
+            Color::Indexed(n) => crossterm::style::Color::AnsiValue(n),
+            Color::Rgb(r, g, b) => crossterm::style::Color::Rgb { r, g, b },
         }
     }
 }
@@ -969,11 +1321,12 @@ bitflags! {
 }
 
 /// Cursor shape
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
 pub enum CursorShape {
     /// Default (terminal dependent)
     Default,
-    /// Blinking block
+    /// Blinking block (デフォルトをブリンクブロックに)
+    #[default]
     BlinkingBlock,
     /// Steady block
     SteadyBlock,
@@ -987,15 +1340,9 @@ pub enum CursorShape {
     SteadyBar,
 }
 
-impl Default for CursorShape {
-    fn default() -> Self {
-        Self::BlinkingBlock  // デフォルトをブリンクブロックに
-    }
-}
-
 impl CursorShape {
     /// Convert to DECSCUSR parameter (for \x1b[N q sequence)
-    pub fn to_decscusr(&self) -> u8 {
+    pub fn to_decscusr(self) -> u8 {
         match self {
             CursorShape::Default => 0,
             CursorShape::BlinkingBlock => 1,
@@ -1022,6 +1369,11 @@ impl CursorShape {
     }
 }
 
+/// Tab stops every 8 columns
+fn default_tab_stops(cols: u16) -> Vec<bool> {
+    (0..cols).map(|col| col > 0 && col % 8 == 0).collect()
+}
+
 /// Cursor state
 #[derive(Clone)]
 pub struct CursorState {
@@ -1030,6 +1382,9 @@ pub struct CursorState {
     pub visible: bool,
     pub shape: CursorShape,
     pub saved: Option<SavedCursor>,
+    /// Set when a printed character filled the right margin, so the next
+    /// one wraps to the left margin; cleared by cursor movement
+    pub wrap_pending: bool,
 }
 
 impl Default for CursorState {
@@ -1040,6 +1395,7 @@ impl Default for CursorState {
             visible: true,
             shape: CursorShape::Default,
             saved: None,
+            wrap_pending: false,
         }
     }
 }
@@ -1050,6 +1406,112 @@ pub struct SavedCursor {
     pub col: u16,
     pub row: u16,
     pub attrs: CellAttrs,
+    pub charsets: CharsetState,
+    pub origin_mode: bool,
+    pub wrap_pending: bool,
+}
+
+/// A character set that can be designated into G0–G3
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Charset {
+    #[default]
+    Ascii,
+    /// DEC Special Graphics (line drawing)
+    DecSpecialGraphics,
+    /// United Kingdom: `#` is the pound sign
+    Uk,
+}
+
+impl Charset {
+    /// Charset selected by the final byte of an SCS sequence (`ESC ( 0`)
+    pub fn from_designator(byte: u8) -> Option<Self> {
+        match byte {
+            b'B' => Some(Charset::Ascii),
+            b'0' => Some(Charset::DecSpecialGraphics),
+            b'A' => Some(Charset::Uk),
+            _ => None,
+        }
+    }
+
+    fn map(self, ch: char) -> char {
+        match self {
+            Charset::Ascii => ch,
+            Charset::Uk => if ch == '#' { '£' } else { ch },
+            Charset::DecSpecialGraphics => match ch {
+                '_' => ' ',
+                '`' => '◆',
+                'a' => '▒',
+                'b' => '␉',
+                'c' => '␌',
+                'd' => '␍',
+                'e' => '␊',
+                'f' => '°',
+                'g' => '±',
+                'h' => '␤',
+                'i' => '␋',
+                'j' => '┘',
+                'k' => '┐',
+                'l' => '┌',
+                'm' => '└',
+                'n' => '┼',
+                'o' => '⎺',
+                'p' => '⎻',
+                'q' => '─',
+                'r' => '⎼',
+                's' => '⎽',
+                't' => '├',
+                'u' => '┤',
+                'v' => '┴',
+                'w' => '┬',
+                'x' => '│',
+                'y' => '≤',
+                'z' => '≥',
+                '{' => 'π',
+                '|' => '≠',
+                '}' => '£',
+                '~' => '·',
+                _ => ch,
+            },
+        }
+    }
+}
+
+/// Character sets designated into G0–G3 and the one invoked into GL
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CharsetState {
+    /// G0–G3
+    pub sets: [Charset; 4],
+    /// Set in GL: 0 after SI, 1 after SO, 2 or 3 after LS2/LS3
+    pub gl: usize,
+    /// Set used for the next character only (SS2/SS3)
+    pub single_shift: Option<usize>,
+}
+
+impl CharsetState {
+    /// Designate `charset` into G`slot`
+    pub fn designate(&mut self, slot: usize, charset: Charset) {
+        self.sets[slot] = charset;
+    }
+
+    /// Invoke G`slot` into GL (SI, SO, LS2, LS3)
+    pub fn invoke(&mut self, slot: usize) {
+        self.gl = slot;
+    }
+
+    /// Use G`slot` for the next character (SS2, SS3)
+    pub fn single_shift(&mut self, slot: usize) {
+        self.single_shift = Some(slot);
+    }
+
+    /// Map a printed character through the invoked set
+    pub fn translate(&mut self, ch: char) -> char {
+        let slot = self.single_shift.take().unwrap_or(self.gl);
+        if ch.is_ascii() {
+            self.sets[slot].map(ch)
+        } else {
+            ch
+        }
+    }
 }
 
 /// Terminal modes
@@ -1059,8 +1521,10 @@ pub struct TerminalModes {
     #[allow(dead_code)]
     pub application_keypad: bool,
     pub auto_wrap: bool,
-    #[allow(dead_code)]
+    /// 6 - Cursor addressing relative to the scroll region and margins
     pub origin_mode: bool,
+    /// 69 - Left and right margins can be set (DECLRMM)
+    pub left_right_margin_mode: bool,
     pub insert_mode: bool,
     pub linefeed_newline: bool,
     pub bracketed_paste: bool,
@@ -1085,6 +1549,7 @@ impl Default for TerminalModes {
             application_keypad: false,
             auto_wrap: true, // Usually enabled by default
             origin_mode: false,
+            left_right_margin_mode: false,
             insert_mode: false,
             linefeed_newline: false,
             bracketed_paste: false,
@@ -0,0 +1,112 @@
+//! Unified event channel for the main loop.
+//!
+//! Everything that can wake the main loop is delivered through one channel,
+//! so the loop blocks in `EventLoop::wait` instead of polling:
+//!
+//! - **PTY output / exit**: sent by each session's reader thread
+//! - **Input / resize**: read from the host console on a dedicated thread
+//! - **Timer ticks**: sent at a fixed interval for time-based UI state
+
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crossterm::event::{self, Event};
+
+/// An event that wakes the main loop
+#[derive(Debug)]
+pub enum AppEvent {
+    /// A session has new output waiting in its channel
+    PtyOutput,
+    /// A session's process has exited and its output has been drained
+    PtyExited,
+    /// Keyboard, mouse, focus or paste event from the host console
+    Input(Event),
+    /// The host console was resized
+    Resize(u16, u16),
+    /// Reading from the host console failed
+    InputError(io::Error),
+    /// Periodic timer tick
+    Tick,
+}
+
+/// Sending half of the event channel, handed to sessions
+pub type EventSender = Sender<AppEvent>;
+
+/// Receiving end of the event channel plus the threads that feed it
+pub struct EventLoop {
+    tx: EventSender,
+    rx: Receiver<AppEvent>,
+}
+
+impl EventLoop {
+    /// Create an event loop with no event sources attached yet
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel();
+        Self { tx, rx }
+    }
+
+    /// Get a sender for an event source
+    pub fn sender(&self) -> EventSender {
+        self.tx.clone()
+    }
+
+    /// Start reading host console input on a background thread
+    pub fn spawn_input_reader(&self) {
+        let tx = self.sender();
+        thread::spawn(move || loop {
+            let app_event = match event::read() {
+                Ok(Event::Resize(cols, rows)) => AppEvent::Resize(cols, rows),
+                Ok(evt) => AppEvent::Input(evt),
+                Err(e) => {
+                    let _ = tx.send(AppEvent::InputError(e));
+                    break;
+                }
+            };
+            if tx.send(app_event).is_err() {
+                break;
+            }
+        });
+    }
+
+    /// Send a `Tick` every `interval` on a background thread
+    pub fn spawn_timer(&self, interval: Duration) {
+        let tx = self.sender();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            if tx.send(AppEvent::Tick).is_err() {
+                break;
+            }
+        });
+    }
+
+    /// Block until the next event arrives.
+    ///
+    /// Console input (including resizes) is returned as a crossterm event.
+    /// PTY and timer events return `None`; the caller handles them by
+    /// running its regular output and timer pass.
+    pub fn wait(&self) -> io::Result<Option<Event>> {
+        self.wait_timeout(None)
+    }
+
+    /// Like `wait`, but returns `None` once `timeout` has passed without
+    /// an event
+    pub fn wait_timeout(&self, timeout: Option<Duration>) -> io::Result<Option<Event>> {
+        // The loop holds a sender itself, so the channel never disconnects
+        let app_event = match timeout {
+            Some(timeout) => match self.rx.recv_timeout(timeout) {
+                Ok(app_event) => app_event,
+                Err(RecvTimeoutError::Timeout) => return Ok(None),
+                Err(RecvTimeoutError::Disconnected) => panic!("event channel closed"),
+            },
+            None => self.rx.recv().expect("event channel closed"),
+        };
+        match app_event {
+            AppEvent::Input(evt) => Ok(Some(evt)),
+            AppEvent::Resize(cols, rows) => Ok(Some(Event::Resize(cols, rows))),
+            AppEvent::InputError(e) => Err(e),
+            AppEvent::PtyOutput | AppEvent::PtyExited | AppEvent::Tick => Ok(None),
+        }
+    }
+}
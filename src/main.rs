@@ -30,7 +30,16 @@
 //! | " | Split horizontal |
 //! | % | Split vertical |
 //! | x | Close pane |
+//! | k | Kill pane (SIGTERM, then SIGKILL after a timeout) |
+//! | I/T/K | Send SIGINT/SIGTERM/SIGKILL to the pane |
 //! | z | Toggle zoom |
+//! | S | Toggle sending input to all panes of the tab |
+//! | e | Cycle pane encoding |
+//! | R | Toggle remain-on-exit for the pane |
+//! | X | Respawn pane (optionally with another command) |
+//! | P | Pipe pane output to a file or command (again to stop) |
+//! | A | Record pane to an asciicast file (again to stop) |
+//! | V | Play an asciicast recording in a new tab |
 //! | Arrow keys | Navigate panes |
 
 mod core;
@@ -39,23 +48,28 @@ mod wm;
 mod history;
 mod config;
 mod copymode;
+mod event_loop;
 
 use std::env;
 use std::io::Write;
 use std::time::Duration;
 
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::cursor::SetCursorStyle;
 use crossterm::execute;
 use tracing::{error, info, Level};
 use tracing_subscriber::FmtSubscriber;
 
-use crate::core::session::Session;
+use crate::core::asciicast::{self, Recorder};
+use crate::core::encoding::Encoding;
+use crate::core::pty::{Signal, SpawnSpec};
+use crate::core::session::{Session, OUTPUT_BYTES_PER_FRAME};
 use crate::ui::{KeyMapper, Renderer, ContextMenu, ContextMenuAction};
-use crate::wm::{WindowManager, SplitDirection};
+use crate::wm::{BellAction, WindowManager, SplitDirection};
 use crate::history::HistorySelector;
 use crate::config::{Config as WtmuxConfig, ColorScheme};
 use crate::copymode::CopyMode;
+use crate::event_loop::EventLoop;
 
 /// Application configuration
 struct Config {
@@ -71,6 +85,10 @@ struct Config {
     shell_from_cli: bool,
     /// Enable debug logging to file
     debug: bool,
+    /// Record the first pane to this asciicast file
+    record: Option<String>,
+    /// Play this asciicast file in a tab at startup
+    play: Option<String>,
 }
 
 impl Default for Config {
@@ -82,6 +100,8 @@ impl Default for Config {
             multipane: true, // Multi-pane mode is now default
             shell_from_cli: false,
             debug: false,  // Logging disabled by default
+            record: None,
+            play: None,
         }
     }
 }
@@ -89,6 +109,12 @@ impl Default for Config {
 /// Version string from Cargo.toml
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Interval of timer ticks driving time-based UI state (overlays, clock)
+const TIMER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Seconds a playback pane moves with the Left/Right keys
+const PLAYBACK_SEEK_SECS: f64 = 5.0;
+
 fn print_version() {
     eprintln!("wtmux {}", VERSION);
 }
@@ -117,6 +143,8 @@ fn print_help() {
     eprintln!("Other options:");
     eprintln!("  -n, --native          Run in native console window");
     eprintln!("  -d, --debug           Enable debug logging to file");
+    eprintln!("  -r, --record <FILE>   Record the first pane to an asciicast v2 file");
+    eprintln!("  --play <FILE>         Play an asciicast file in a new tab (multi-pane mode)");
     eprintln!("  -v, --version         Show version");
     eprintln!("  -h, --help            Show this help");
     eprintln!();
@@ -124,6 +152,8 @@ fn print_help() {
     eprintln!("  Ctrl+B, c             New window (tab)");
     eprintln!("  Ctrl+B, &             Kill window (tab)");
     eprintln!("  Ctrl+B, x             Kill pane");
+    eprintln!("  Ctrl+B, k             Kill pane gracefully (SIGTERM, then SIGKILL)");
+    eprintln!("  Ctrl+B, I / T / K     Send SIGINT / SIGTERM / SIGKILL to the pane");
     eprintln!("  Ctrl+B, \"             Split pane horizontally (top/bottom)");
     eprintln!("  Ctrl+B, %             Split pane vertically (left/right)");
     eprintln!("  Ctrl+B, n             Next window");
@@ -134,6 +164,18 @@ fn print_help() {
     eprintln!("  Ctrl+B, ;             Previous pane");
     eprintln!("  Ctrl+B, Arrow         Move to pane in direction");
     eprintln!("  Ctrl+B, z             Toggle pane zoom");
+    eprintln!("  Ctrl+B, S             Toggle sending input to all panes (synchronize)");
+    eprintln!("  Ctrl+B, e             Cycle pane encoding");
+    eprintln!("  Ctrl+B, R             Toggle remain-on-exit for the pane");
+    eprintln!("  Ctrl+B, X             Respawn pane (edit command, Tab: keep scrollback)");
+    eprintln!("  Ctrl+B, P             Pipe pane output to a file or |command (again: stop)");
+    eprintln!("  Ctrl+B, A             Record pane to an asciicast file (again: stop)");
+    eprintln!("  Ctrl+B, V             Play an asciicast recording in a new tab");
+    eprintln!("                        (Space: pause, Left/Right: seek, +/-: speed, q: close)");
+    eprintln!();
+    eprintln!("Exited pane (remain-on-exit):");
+    eprintln!("  r                     Respawn the pane's command");
+    eprintln!("  q                     Close the pane");
     eprintln!();
     eprintln!("Snippet selector (at command prompt, not in vim/apps):");
     eprintln!("  Ctrl+R                Open snippet selector");
@@ -220,6 +262,20 @@ fn parse_args() -> Result<Config, String> {
             "-d" | "--debug" => {
                 config.debug = true;
             }
+            "-r" | "--record" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("Missing recording file argument".to_string());
+                }
+                config.record = Some(args[i].clone());
+            }
+            "--play" => {
+                i += 1;
+                if i >= args.len() {
+                    return Err("Missing recording file argument".to_string());
+                }
+                config.play = Some(args[i].clone());
+            }
             arg => {
                 return Err(format!("Unknown argument: {}. Use -h for help.", arg));
             }
@@ -279,6 +335,30 @@ fn detect_terminal_env() -> String {
     "Windows Console".to_string()
 }
 
+/// Detect the host terminal environment
+#[cfg(not(windows))]
+fn detect_terminal_env() -> String {
+    if let Ok(program) = env::var("TERM_PROGRAM") {
+        return program;
+    }
+    if env::var("WSL_DISTRO_NAME").is_ok() {
+        return "WSL".to_string();
+    }
+    env::var("TERM").unwrap_or_else(|_| "Unknown".to_string())
+}
+
+/// Default shell when none is configured
+#[cfg(windows)]
+fn default_shell() -> String {
+    "cmd.exe".to_string()
+}
+
+/// Default shell when none is configured
+#[cfg(not(windows))]
+fn default_shell() -> String {
+    env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+}
+
 /// Get shell name from command
 fn get_shell_name(shell_cmd: &str) -> &str {
     if shell_cmd.contains("pwsh") {
@@ -296,29 +376,19 @@ fn get_shell_name(shell_cmd: &str) -> &str {
     }
 }
 
+/// Ring the host terminal's bell
+fn ring_host_bell() {
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(b"\x07");
+    let _ = stdout.flush();
+}
+
 /// Reset cursor shape to default block cursor
 fn reset_cursor_shape() {
     let mut stdout = std::io::stdout();
     let _ = execute!(stdout, SetCursorStyle::SteadyBlock);
 }
 
-/// Get encoding name
-fn get_encoding_name(codepage: Option<u32>) -> &'static str {
-    match codepage {
-        Some(65001) => "UTF-8",
-        Some(932) => "Shift-JIS",
-        Some(cp) => {
-            // Return a static str for common codepages
-            match cp {
-                20932 => "EUC-JP",
-                50220 => "ISO-2022-JP",
-                _ => "Custom"
-            }
-        }
-        None => "Shift-JIS"
-    }
-}
-
 /// Relaunch in a native cmd.exe window
 #[cfg(windows)]
 fn relaunch_in_cmd() -> ! {
@@ -423,8 +493,11 @@ fn ensure_native_console() -> bool {
 
 fn main() -> anyhow::Result<()> {
     // Check for -n/--native flag early (before full parsing)
+    #[cfg(windows)]
     let args: Vec<String> = env::args().collect();
+    #[cfg(windows)]
     let wants_native = args.iter().any(|a| a == "-n" || a == "--native");
+    #[cfg(windows)]
     let no_relaunch = args.iter().any(|a| a == "--no-relaunch");
     
     // If -n flag and running in Windows Terminal, relaunch in native console
@@ -483,25 +556,12 @@ fn main() -> anyhow::Result<()> {
     env::set_var("WTMUX", "1");
     env::set_var("WTMUX_VERSION", env!("CARGO_PKG_VERSION"));
 
-    // Check platform
-    #[cfg(not(windows))]
-    {
-        eprintln!("wtmux currently only supports Windows with ConPTY.");
-        eprintln!("Running in demo mode...");
-        run_demo()?;
-        return Ok(());
-    }
-
-    #[cfg(windows)]
-    {
-        run_terminal(config)?;
-    }
+    run_terminal(config)?;
 
     Ok(())
 }
 
-/// Run the terminal (Windows only)
-#[cfg(windows)]
+/// Run the terminal
 fn run_terminal(mut config: Config) -> anyhow::Result<()> {
     use crossterm::terminal;
     
@@ -515,9 +575,9 @@ fn run_terminal(mut config: Config) -> anyhow::Result<()> {
             config.shell = Some(shell.clone());
         }
     }
-    // Default to cmd.exe if still not set
+    // Default to the platform shell if still not set
     if config.shell.is_none() {
-        config.shell = Some("cmd.exe".to_string());
+        config.shell = Some(default_shell());
     }
     
     // Codepage from config file (CLI always overrides since it has default)
@@ -530,9 +590,9 @@ fn run_terminal(mut config: Config) -> anyhow::Result<()> {
     
     // Detect terminal environment
     let terminal_env = detect_terminal_env();
-    let shell_cmd_str = config.shell.clone().unwrap_or_else(|| "cmd.exe".to_string());
+    let shell_cmd_str = config.shell.clone().unwrap_or_else(default_shell);
     let shell_name = get_shell_name(&shell_cmd_str);
-    let encoding_name = get_encoding_name(config.codepage);
+    let encoding_name = Encoding::for_pty_codepage(config.codepage).name();
     
     // Log environment info
     info!("Host terminal: {}", terminal_env);
@@ -551,13 +611,22 @@ fn run_terminal(mut config: Config) -> anyhow::Result<()> {
 
     // Simple single-pane mode
     // Create session (ConPTY always outputs UTF-8)
+    let events = EventLoop::new();
     let mut session = Session::new(1, cols, rows);
+    session.set_event_sender(events.sender());
 
     // Start shell with optional codepage
-    if let Err(e) = session.start_with_codepage(Some(&shell_cmd_str), config.codepage) {
+    let spec = SpawnSpec::from_command_line(&shell_cmd_str)
+        .codepage(config.codepage)
+        .pane_identity(1, 1);
+    if let Err(e) = session.start(&spec) {
         error!("Failed to start shell: {}", e);
         return Err(e.into());
     }
+    if let Some(path) = &config.record {
+        let recorder = Recorder::create(path, cols, rows, session.encoding(), None)?;
+        session.set_recorder(Some(recorder));
+    }
 
     // Initialize renderer and run with guaranteed cleanup
     let mut renderer = Renderer::new();
@@ -569,7 +638,7 @@ fn run_terminal(mut config: Config) -> anyhow::Result<()> {
     let _ = std::io::stdout().flush();
 
     // Run main loop
-    let result = run_main_loop(&mut session, &mut renderer);
+    let result = run_main_loop(&mut session, &mut renderer, &events);
 
     // Cleanup - multiple attempts to ensure it works
     let _ = renderer.cleanup();
@@ -587,7 +656,6 @@ fn run_terminal(mut config: Config) -> anyhow::Result<()> {
 }
 
 /// Run terminal in multi-pane mode
-#[cfg(windows)]
 fn run_terminal_wm(config: Config, cols: u16, rows: u16, shell_name: &str, encoding_name: &str, terminal_env: &str, wtmux_config: WtmuxConfig) -> anyhow::Result<()> {
     use crossterm::terminal;
     use crate::ui::WmRenderer;
@@ -603,10 +671,26 @@ fn run_terminal_wm(config: Config, cols: u16, rows: u16, shell_name: &str, encod
     let mut wm = WindowManager::new(
         cols, 
         rows, 
-        config.shell.clone(),
-        config.codepage,
+        config.shell
+            .as_deref()
+            .map(SpawnSpec::from_command_line)
+            .unwrap_or_default()
+            .codepage(config.codepage),
         prefix_key,
     );
+    wm.remain_on_exit = wtmux_config.remain_on_exit;
+    wm.inherit_cwd = wtmux_config.inherit_cwd;
+    wm.automatic_rename = wtmux_config.automatic_rename;
+    wm.kill_timeout = Duration::from_secs(wtmux_config.kill_timeout);
+    wm.monitor_activity = wtmux_config.monitor_activity;
+    wm.monitor_silence = (wtmux_config.monitor_silence > 0)
+        .then(|| Duration::from_secs(wtmux_config.monitor_silence));
+    wm.alert_messages = wtmux_config.alert_messages;
+    wm.bell_action = BellAction::parse(&wtmux_config.bell_action).unwrap_or_default();
+    wm.visual_bell = wtmux_config.visual_bell;
+    wm.audible_bell = wtmux_config.audible_bell;
+    let events = EventLoop::new();
+    wm.set_event_sender(events.sender());
     
     // Start initial session
     if let Err(e) = wm.start() {
@@ -617,6 +701,14 @@ fn run_terminal_wm(config: Config, cols: u16, rows: u16, shell_name: &str, encod
     // Force resize to ensure PTY has correct size
     wm.resize(cols, rows);
 
+    if let Some(path) = &config.record {
+        wm.start_recording(path).map_err(|e| anyhow::anyhow!("Failed to record to {}: {}", path, e))?;
+    }
+
+    if let Some(path) = &config.play {
+        wm.open_playback(path).map_err(|e| anyhow::anyhow!("Failed to play {}: {}", path, e))?;
+    }
+
     // Initialize renderer with color scheme
     let mut renderer = WmRenderer::with_color_scheme(color_scheme);
     renderer.init()?;
@@ -627,7 +719,7 @@ fn run_terminal_wm(config: Config, cols: u16, rows: u16, shell_name: &str, encod
     let _ = std::io::stdout().flush();
 
     // Run main loop
-    let result = run_wm_main_loop(&mut wm, &mut renderer);
+    let result = run_wm_main_loop(&mut wm, &mut renderer, &events);
 
     // Cleanup
     let _ = renderer.cleanup();
@@ -642,9 +734,9 @@ fn run_terminal_wm(config: Config, cols: u16, rows: u16, shell_name: &str, encod
 }
 
 /// Main event loop for window manager
-#[cfg(windows)]
-fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer) -> anyhow::Result<()> {
-    let poll_timeout = Duration::from_millis(10);
+fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer, events: &EventLoop) -> anyhow::Result<()> {
+    events.spawn_input_reader();
+    events.spawn_timer(TIMER_INTERVAL);
     let mut selector = HistorySelector::new();
     
     // Theme selector state
@@ -663,17 +755,29 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
     // Window rename mode state
     let mut rename_mode = false;
     let mut rename_buffer = String::new();
+
+    // Respawn prompt state
+    let mut respawn_mode = false;
+    let mut respawn_buffer = String::new();
+    let mut respawn_keep_scrollback = true;
+
+    // Recording prompt state
+    let mut record_mode = false;
+    let mut record_buffer = String::new();
+
+    // Playback prompt state
+    let mut play_mode = false;
+    let mut play_buffer = String::new();
+
+    // Pipe-pane prompt state
+    let mut pipe_mode = false;
+    let mut pipe_buffer = String::new();
+    let mut pipe_strip_escapes = false;
     
     // Context menu state
     let mut context_menu = ContextMenu::new();
 
     loop {
-        // Check if any session is still running
-        if !wm.is_running() {
-            info!("All sessions ended");
-            break;
-        }
-        
         // Check pane numbers timeout
         if pane_numbers_visible && pane_numbers_timer.elapsed() >= pane_numbers_duration {
             pane_numbers_visible = false;
@@ -681,16 +785,19 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
 
         // Process output from all panes
         let has_output = wm.process_output();
+        if wm.take_bell() {
+            ring_host_bell();
+        }
         
-        // Check again after processing output (panes may have exited)
+        // Stop once no pane is left (panes may have exited)
         if !wm.is_running() {
             info!("All sessions ended after output processing");
             break;
         }
         
         // Render based on current mode
-        if copy_mode.active || rename_mode || context_menu.visible {
-            // In copy mode, rename mode, or context menu, only render on key events
+        if copy_mode.active || rename_mode || respawn_mode || pipe_mode || record_mode || play_mode || context_menu.visible {
+            // In copy mode, prompts, or context menu, only render on key events
             // (rendering happens in the key handler below)
         } else if has_output {
             if theme_selector_visible {
@@ -702,9 +809,10 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
             }
         }
 
-        // Poll for events
-        if event::poll(poll_timeout)? {
-            match event::read()? {
+        // Wait for the next event; PTY output and timer ticks just
+        // go around the loop again
+        if let Some(evt) = events.wait_timeout(wm.next_wakeup())? {
+            match evt {
                 Event::Key(key_event) => {
                     if key_event.kind != KeyEventKind::Press {
                         continue;
@@ -887,16 +995,129 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                             KeyCode::Backspace => {
                                 rename_buffer.pop();
                             }
-                            KeyCode::Char(c) => {
-                                if rename_buffer.len() < 30 {
-                                    rename_buffer.push(c);
-                                }
+                            KeyCode::Char(c) if rename_buffer.len() < 30 => {
+                                rename_buffer.push(c);
                             }
                             _ => {}
                         }
                         renderer.render_with_rename(wm, &rename_buffer)?;
                         continue;
                     }
+
+                    // Handle respawn prompt
+                    if respawn_mode {
+                        match key_event.code {
+                            KeyCode::Esc => {
+                                respawn_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Enter => {
+                                if let Err(e) = wm.respawn_pane(Some(&respawn_buffer), respawn_keep_scrollback) {
+                                    error!("Failed to respawn pane: {}", e);
+                                }
+                                respawn_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Tab => {
+                                respawn_keep_scrollback = !respawn_keep_scrollback;
+                            }
+                            KeyCode::Backspace => {
+                                respawn_buffer.pop();
+                            }
+                            KeyCode::Char(c) => {
+                                respawn_buffer.push(c);
+                            }
+                            _ => {}
+                        }
+                        renderer.render_with_respawn(wm, &respawn_buffer, respawn_keep_scrollback)?;
+                        continue;
+                    }
+
+                    // Handle recording prompt
+                    if record_mode {
+                        match key_event.code {
+                            KeyCode::Esc => {
+                                record_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Enter => {
+                                // The status bar reports failures
+                                let _ = wm.start_recording(&record_buffer);
+                                record_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Backspace => {
+                                record_buffer.pop();
+                            }
+                            KeyCode::Char(c) => {
+                                record_buffer.push(c);
+                            }
+                            _ => {}
+                        }
+                        renderer.render_with_record(wm, &record_buffer)?;
+                        continue;
+                    }
+
+                    // Handle playback prompt
+                    if play_mode {
+                        match key_event.code {
+                            KeyCode::Esc => {
+                                play_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Enter => {
+                                // The status bar reports failures
+                                let _ = wm.open_playback(&play_buffer);
+                                play_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Backspace => {
+                                play_buffer.pop();
+                            }
+                            KeyCode::Char(c) => {
+                                play_buffer.push(c);
+                            }
+                            _ => {}
+                        }
+                        renderer.render_with_play(wm, &play_buffer)?;
+                        continue;
+                    }
+
+                    // Handle pipe-pane prompt
+                    if pipe_mode {
+                        match key_event.code {
+                            KeyCode::Esc => {
+                                pipe_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Enter => {
+                                // The status bar reports failures
+                                let _ = wm.pipe_pane(&pipe_buffer, pipe_strip_escapes);
+                                pipe_mode = false;
+                                renderer.render(wm)?;
+                                continue;
+                            }
+                            KeyCode::Tab => {
+                                pipe_strip_escapes = !pipe_strip_escapes;
+                            }
+                            KeyCode::Backspace => {
+                                pipe_buffer.pop();
+                            }
+                            KeyCode::Char(c) => {
+                                pipe_buffer.push(c);
+                            }
+                            _ => {}
+                        }
+                        renderer.render_with_pipe(wm, &pipe_buffer, pipe_strip_escapes)?;
+                        continue;
+                    }
                     
                     // Handle pane numbers mode - select pane by number
                     if pane_numbers_visible {
@@ -919,14 +1140,10 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                                 theme_selector_visible = false;
                             }
                             KeyCode::Up => {
-                                if theme_selector_index > 0 {
-                                    theme_selector_index -= 1;
-                                }
+                                theme_selector_index = theme_selector_index.saturating_sub(1);
                             }
-                            KeyCode::Down => {
-                                if theme_selector_index + 1 < theme_list.len() {
-                                    theme_selector_index += 1;
-                                }
+                            KeyCode::Down if theme_selector_index + 1 < theme_list.len() => {
+                                theme_selector_index += 1;
                             }
                             KeyCode::Enter => {
                                 let scheme_name = theme_list[theme_selector_index];
@@ -984,7 +1201,7 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                                 // Number selection only when query is empty
                                 if selector.query.is_empty() && c.is_ascii_digit() {
                                     if let Some(num) = c.to_digit(10) {
-                                        if num >= 1 && num <= 9 {
+                                        if (1..=9).contains(&num) {
                                             if let Some(command) = selector.select_number(num as usize) {
                                                 // Clear current input and insert
                                                 wm.clear_current_input();
@@ -1021,6 +1238,24 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                                 wm.close_pane();
                                 wm.prefix_mode = false;
                             }
+                            // Kill pane gracefully, forcing it after a timeout (k)
+                            KeyCode::Char('k') => {
+                                wm.kill_pane();
+                                wm.prefix_mode = false;
+                            }
+                            // Send SIGINT / SIGTERM / SIGKILL to the pane (I, T, K)
+                            KeyCode::Char('I') => {
+                                let _ = wm.signal_pane(Signal::Interrupt);
+                                wm.prefix_mode = false;
+                            }
+                            KeyCode::Char('T') => {
+                                let _ = wm.signal_pane(Signal::Terminate);
+                                wm.prefix_mode = false;
+                            }
+                            KeyCode::Char('K') => {
+                                let _ = wm.signal_pane(Signal::Kill);
+                                wm.prefix_mode = false;
+                            }
                             // Kill window/tab (tmux: &)
                             KeyCode::Char('&') => {
                                 wm.close_tab();
@@ -1116,6 +1351,55 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                                 reset_cursor_shape();
                                 wm.prefix_mode = false;
                             }
+                            // Cycle the focused pane's encoding (e)
+                            KeyCode::Char('e') => {
+                                wm.cycle_encoding();
+                                wm.prefix_mode = false;
+                            }
+                            // Toggle remain-on-exit for the focused pane (R)
+                            KeyCode::Char('R') => {
+                                wm.toggle_remain_on_exit();
+                                wm.prefix_mode = false;
+                            }
+                            // Respawn pane, with the command pre-filled for editing (X)
+                            KeyCode::Char('X') => {
+                                respawn_mode = true;
+                                respawn_buffer = wm.focused_command_line().unwrap_or_default();
+                                wm.prefix_mode = false;
+                                renderer.render_with_respawn(wm, &respawn_buffer, respawn_keep_scrollback)?;
+                                continue;
+                            }
+                            // Pipe pane output to a file or command, or stop piping (P)
+                            KeyCode::Char('P') => {
+                                wm.prefix_mode = false;
+                                if !wm.stop_pipe_pane() {
+                                    pipe_mode = true;
+                                    renderer.render_with_pipe(wm, &pipe_buffer, pipe_strip_escapes)?;
+                                    continue;
+                                }
+                            }
+                            // Record pane to an asciicast file, or stop recording (A)
+                            KeyCode::Char('A') => {
+                                wm.prefix_mode = false;
+                                if !wm.stop_recording() {
+                                    record_mode = true;
+                                    record_buffer = asciicast::default_file_name();
+                                    renderer.render_with_record(wm, &record_buffer)?;
+                                    continue;
+                                }
+                            }
+                            // Play an asciicast recording in a new tab (V)
+                            KeyCode::Char('V') => {
+                                wm.prefix_mode = false;
+                                play_mode = true;
+                                renderer.render_with_play(wm, &play_buffer)?;
+                                continue;
+                            }
+                            // Send input to all panes of the tab (tmux: synchronize-panes)
+                            KeyCode::Char('S') => {
+                                wm.toggle_synchronize_panes();
+                                wm.prefix_mode = false;
+                            }
                             // Zoom pane toggle (tmux: z)
                             KeyCode::Char('z') => {
                                 wm.toggle_zoom();
@@ -1209,12 +1493,44 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                     }
 
                     // Check for prefix key (configurable, default: Ctrl+B)
-                    if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                        if key_event.code == KeyCode::Char(wm.prefix_key.char) {
-                            wm.prefix_mode = true;
-                            renderer.render(wm)?;
-                            continue;
+                    if key_event.modifiers.contains(KeyModifiers::CONTROL)
+                        && key_event.code == KeyCode::Char(wm.prefix_key.char)
+                    {
+                        wm.prefix_mode = true;
+                        renderer.render(wm)?;
+                        continue;
+                    }
+
+                    // A playback pane is read-only: keys control the player
+                    if wm.focused_pane_playback() {
+                        match key_event.code {
+                            KeyCode::Char(' ') => wm.toggle_playback_pause(),
+                            KeyCode::Left => wm.seek_playback(-PLAYBACK_SEEK_SECS),
+                            KeyCode::Right => wm.seek_playback(PLAYBACK_SEEK_SECS),
+                            KeyCode::Char('+') | KeyCode::Char('=') => wm.change_playback_speed(2.0),
+                            KeyCode::Char('-') => wm.change_playback_speed(0.5),
+                            KeyCode::Char('q') => wm.close_playback(),
+                            _ => {}
                         }
+                        renderer.render(wm)?;
+                        continue;
+                    }
+
+                    // An exited pane kept by remain-on-exit: r respawns, q closes
+                    if wm.focused_pane_dead() {
+                        match key_event.code {
+                            KeyCode::Char('r') => {
+                                if let Err(e) = wm.respawn_pane(None, true) {
+                                    error!("Failed to respawn pane: {}", e);
+                                }
+                            }
+                            KeyCode::Char('q') => {
+                                wm.close_dead_pane();
+                            }
+                            _ => {}
+                        }
+                        renderer.render(wm)?;
+                        continue;
                     }
 
                     // Check for Ctrl+R (selector) - only when not in alternate screen
@@ -1276,11 +1592,11 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                                 context_menu.hide();
                                 renderer.render(wm)?;
                             }
-                            MouseEventKind::Moved | MouseEventKind::Drag(_) => {
-                                // Highlight item under cursor
-                                if context_menu.update_hover(mouse_event.column, mouse_event.row) {
-                                    renderer.render_context_menu_only(&context_menu)?;
-                                }
+                            // Highlight item under cursor
+                            MouseEventKind::Moved | MouseEventKind::Drag(_)
+                                if context_menu.update_hover(mouse_event.column, mouse_event.row) =>
+                            {
+                                renderer.render_context_menu_only(&context_menu)?;
                             }
                             _ => {}
                         }
@@ -1321,7 +1637,7 @@ fn run_wm_main_loop(wm: &mut WindowManager, renderer: &mut crate::ui::WmRenderer
                                 
                                 let bytes = KeyMapper::encode_mouse_event(&adjusted_event, sgr, urxvt);
                                 if !bytes.is_empty() {
-                                    let _ = wm.write(&bytes);
+                                    let _ = wm.write_focused(&bytes);
                                 }
                                 continue;
                             }
@@ -1410,9 +1726,8 @@ fn execute_context_menu_action(wm: &mut WindowManager, action: ContextMenuAction
 }
 
 /// Main event loop
-#[cfg(windows)]
-fn run_main_loop(session: &mut Session, renderer: &mut Renderer) -> anyhow::Result<()> {
-    let poll_timeout = Duration::from_millis(10);
+fn run_main_loop(session: &mut Session, renderer: &mut Renderer, events: &EventLoop) -> anyhow::Result<()> {
+    events.spawn_input_reader();
 
     loop {
         // Check if session is still running at the start of each iteration
@@ -1422,11 +1737,14 @@ fn run_main_loop(session: &mut Session, renderer: &mut Renderer) -> anyhow::Resu
         }
 
         // Process PTY output
-        match session.process_output() {
+        match session.process_output(OUTPUT_BYTES_PER_FRAME) {
             Ok(true) => {
                 // Output processed, render
                 renderer.render(&session.state)?;
                 session.state.active_screen_mut().clear_dirty();
+                if std::mem::take(&mut session.state.bell) {
+                    ring_host_bell();
+                }
             }
             Ok(false) => {
                 // No output, check again
@@ -1444,9 +1762,8 @@ fn run_main_loop(session: &mut Session, renderer: &mut Renderer) -> anyhow::Resu
             }
         }
 
-        // Process input events
-        if event::poll(poll_timeout)? {
-            let evt = event::read()?;
+        // Wait for input; PTY output just goes around the loop again
+        if let Some(evt) = events.wait()? {
             // Log all events to debug file
             renderer.log_mouse_event(&format!("Event received: {:?}", evt));
             
@@ -1808,39 +2125,3 @@ fn copy_to_clipboard_windows(text: &str) -> Result<(), ()> {
     
     Ok(())
 }
-
-/// Demo mode for non-Windows platforms
-#[cfg(not(windows))]
-fn run_demo() -> anyhow::Result<()> {
-    use crate::core::term::TerminalState;
-    use crate::ui::DebugRenderer;
-
-    println!("=== wtmux Demo Mode ===\n");
-
-    // Create a terminal state
-    let mut state = TerminalState::new(80, 24);
-
-    // Simulate some output
-    let demo_output = concat!(
-        "\x1b[32mWelcome to wtmux!\x1b[0m\r\n",
-        "\r\n",
-        "This is a \x1b[1mbold\x1b[0m and \x1b[4munderlined\x1b[0m text.\r\n",
-        "Colors: \x1b[31mRed\x1b[0m \x1b[32mGreen\x1b[0m \x1b[34mBlue\x1b[0m\r\n",
-        "\r\n",
-        "日本語テスト: こんにちは世界！\r\n",
-        "\r\n",
-        "\x1b[7mInverse text\x1b[0m\r\n",
-        "\r\n",
-        "PS C:\\Users\\demo> \x1b[?25h",
-    );
-
-    // Create a session and feed the demo output
-    let mut session = Session::new(1, 80, 24);
-    session.feed_bytes(demo_output.as_bytes());
-
-    // Render to debug output
-    println!("{}", DebugRenderer::render(&session.state));
-
-    println!("\nDemo complete. Build on Windows to use ConPTY.");
-    Ok(())
-}
@@ -127,11 +127,9 @@ impl KeyMapper {
         }
 
         // Ctrl + Alt + letter
-        if mods.contains(Modifiers::CTRL) && mods.contains(Modifiers::ALT) {
-            if ch.is_ascii_alphabetic() {
-                let ctrl_code = (ch.to_ascii_lowercase() as u8) - b'a' + 1;
-                return vec![0x1B, ctrl_code];
-            }
+        if mods.contains(Modifiers::CTRL) && mods.contains(Modifiers::ALT) && ch.is_ascii_alphabetic() {
+            let ctrl_code = (ch.to_ascii_lowercase() as u8) - b'a' + 1;
+            return vec![0x1B, ctrl_code];
         }
 
         // Alt + key = ESC + key
@@ -279,7 +277,7 @@ impl KeyMapper {
             // X10 mode: \x1b[MCbCxCy (encoded as bytes + 32)
             // Only works for coordinates <= 223
             if x <= 223 && y <= 223 {
-                vec![0x1b, b'[', b'M', (cb + 32) as u8, (x as u8 + 32), (y as u8 + 32)]
+                vec![0x1b, b'[', b'M', (cb + 32), (x as u8 + 32), (y as u8 + 32)]
             } else {
                 // Coordinates out of range for X10 mode
                 vec![]
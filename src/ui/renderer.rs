@@ -381,7 +381,7 @@ impl Renderer {
     fn render_full<W: Write>(&self, stdout: &mut W, state: &TerminalState) -> io::Result<()> {
         let screen = state.active_screen();
         let num_rows = state.rows as usize;
-        let num_cols = state.cols as u16;
+        let num_cols = state.cols;
         let has_selection = state.selection.is_some();
 
         // Hide cursor during rendering
@@ -465,7 +465,7 @@ impl Renderer {
     fn render_dirty<W: Write>(&self, stdout: &mut W, state: &TerminalState) -> io::Result<()> {
         let screen = state.active_screen();
         let has_selection = state.selection.is_some();
-        let num_cols = state.cols as u16;
+        let num_cols = state.cols;
 
         execute!(stdout, Hide)?;
 
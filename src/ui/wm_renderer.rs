@@ -34,7 +34,7 @@ use crossterm::{
     },
     terminal::{self, Clear, ClearType},
 };
-use unicode_width::UnicodeWidthChar;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::wm::{WindowManager, Pane, BorderStyle};
 use crate::core::term::{AttrFlags, CellAttrs, Color};
@@ -115,6 +115,22 @@ impl BorderChars {
             cross: '┼',
         }
     }
+
+    fn double() -> Self {
+        Self {
+            top_left: '╔',
+            top_right: '╗',
+            bottom_left: '╚',
+            bottom_right: '╝',
+            horizontal: '═',
+            vertical: '║',
+            t_down: '╦',
+            t_up: '╩',
+            t_left: '╣',
+            t_right: '╠',
+            cross: '╬',
+        }
+    }
 }
 
 /// Multi-pane renderer
@@ -435,7 +451,7 @@ impl WmRenderer {
 
         // Render border if needed
         if pane.border != BorderStyle::None {
-            self.render_border(stdout, pane, y_offset)?;
+            self.render_border(stdout, pane, y_offset, tab.synchronize_panes)?;
         }
 
         execute!(stdout, ResetColor)?;
@@ -490,9 +506,102 @@ impl WmRenderer {
         })
     }
 
+    /// Render with respawn prompt overlay
+    pub fn render_with_respawn(&mut self, wm: &WindowManager, command: &str, keep_scrollback: bool) -> io::Result<()> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        if !self.initialized {
+            self.init()?;
+        }
+
+        let help = format!(
+            "Enter:Run  Tab:Keep scrollback [{}]  Esc:Cancel",
+            if keep_scrollback { "on" } else { "off" }
+        );
+        with_frame(&mut out, |out| {
+            self.render_tab_bar(out, wm)?;
+            self.render_panes(out, wm)?;
+            self.render_status_bar(out, wm)?;
+            self.render_prompt_popup(out, wm, "Respawn Pane", command, &help, 60)?;
+            Ok(())
+        })
+    }
+
+    /// Render with pipe-pane prompt overlay
+    pub fn render_with_pipe(&mut self, wm: &WindowManager, target: &str, strip_escapes: bool) -> io::Result<()> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        if !self.initialized {
+            self.init()?;
+        }
+
+        let help = format!(
+            "Enter:Start  Tab:Strip escapes [{}]  Esc:Cancel",
+            if strip_escapes { "on" } else { "off" }
+        );
+        with_frame(&mut out, |out| {
+            self.render_tab_bar(out, wm)?;
+            self.render_panes(out, wm)?;
+            self.render_status_bar(out, wm)?;
+            self.render_prompt_popup(out, wm, "Pipe Pane to file or |command", target, &help, 60)?;
+            Ok(())
+        })
+    }
+
+    /// Render with the prompt for an asciicast recording file
+    pub fn render_with_record(&mut self, wm: &WindowManager, path: &str) -> io::Result<()> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        if !self.initialized {
+            self.init()?;
+        }
+
+        with_frame(&mut out, |out| {
+            self.render_tab_bar(out, wm)?;
+            self.render_panes(out, wm)?;
+            self.render_status_bar(out, wm)?;
+            self.render_prompt_popup(out, wm, "Record Pane (asciicast)", path, "Enter:Record  Esc:Cancel", 60)?;
+            Ok(())
+        })
+    }
+
+    /// Render with the prompt for an asciicast file to play back
+    pub fn render_with_play(&mut self, wm: &WindowManager, path: &str) -> io::Result<()> {
+        let stdout = io::stdout();
+        let mut out = stdout.lock();
+
+        if !self.initialized {
+            self.init()?;
+        }
+
+        with_frame(&mut out, |out| {
+            self.render_tab_bar(out, wm)?;
+            self.render_panes(out, wm)?;
+            self.render_status_bar(out, wm)?;
+            self.render_prompt_popup(out, wm, "Play Recording (asciicast)", path, "Enter:Play  Esc:Cancel", 60)?;
+            Ok(())
+        })
+    }
+
     /// Render rename popup in center of screen
     fn render_rename_popup<W: Write>(&self, stdout: &mut W, wm: &WindowManager, rename_buffer: &str) -> io::Result<()> {
-        let box_width = 40.min(wm.width.saturating_sub(4)) as usize;
+        self.render_prompt_popup(stdout, wm, "Rename Window", rename_buffer, "Enter:OK  Esc:Cancel", 40)
+    }
+
+    /// Render a one-line input popup in center of screen
+    fn render_prompt_popup<W: Write>(
+        &self,
+        stdout: &mut W,
+        wm: &WindowManager,
+        title: &str,
+        input: &str,
+        help: &str,
+        width: u16,
+    ) -> io::Result<()> {
+        let box_width = width.min(wm.width.saturating_sub(4)) as usize;
         let box_height = 5;
         let start_x = ((wm.width as usize).saturating_sub(box_width)) / 2;
         let start_y = ((wm.height as usize).saturating_sub(box_height)) / 2;
@@ -505,8 +614,8 @@ impl WmRenderer {
 
         // Top border
         execute!(stdout, MoveTo(start_x as u16, start_y as u16))?;
-        write!(stdout, "┌─ Rename Window ")?;
-        let title_len = 16;
+        write!(stdout, "┌─ {} ", title)?;
+        let title_len = title.chars().count() + 3;
         for _ in 0..(box_width.saturating_sub(title_len + 2)) {
             write!(stdout, "─")?;
         }
@@ -518,11 +627,12 @@ impl WmRenderer {
 
         // Input line
         execute!(stdout, MoveTo(start_x as u16, (start_y + 2) as u16))?;
-        let input_display = if rename_buffer.len() > box_width - 6 {
-            &rename_buffer[rename_buffer.len() - (box_width - 6)..]
-        } else {
-            rename_buffer
-        };
+        // Show the end of long input, where the cursor is
+        let input_len = input.chars().count();
+        let input_display: String = input
+            .chars()
+            .skip(input_len.saturating_sub(box_width - 6))
+            .collect();
         write!(stdout, "│ {:<width$} │", format!("{}█", input_display), width = box_width - 4)?;
 
         // Empty line
@@ -531,7 +641,6 @@ impl WmRenderer {
 
         // Bottom border with help
         execute!(stdout, MoveTo(start_x as u16, (start_y + 4) as u16))?;
-        let help = "Enter:OK  Esc:Cancel";
         let help_padding = (box_width.saturating_sub(help.len() + 4)) / 2;
         write!(stdout, "└")?;
         for _ in 0..help_padding {
@@ -822,10 +931,18 @@ impl WmRenderer {
         write!(stdout, "{:width$}", "", width = wm.width as usize)?;
         execute!(stdout, MoveTo(0, 0))?;
 
-        // Render tabs
+        // Render tabs; the visual bell also flashes the active tab, as
+        // borderless panes have no border to flash
+        let bell_flash = wm.active_tab()
+            .is_some_and(|tab| tab.panes.values().any(|pane| pane.bell_flash.is_some()));
         let tabs = wm.tab_info();
         for (i, (_id, name, active)) in tabs.iter().enumerate() {
-            if *active {
+            if *active && bell_flash {
+                execute!(stdout, 
+                    SetBackgroundColor(cs.status_prefix_bg.to_crossterm()), 
+                    SetForegroundColor(cs.status_prefix_fg.to_crossterm())
+                )?;
+            } else if *active {
                 execute!(stdout, 
                     SetBackgroundColor(cs.tab_active_bg.to_crossterm()), 
                     SetForegroundColor(cs.tab_active_fg.to_crossterm())
@@ -882,20 +999,21 @@ impl WmRenderer {
         if tab.is_zoomed() {
             if let Some(zoomed_id) = tab.zoomed_pane_id() {
                 if let Some(pane) = tab.panes.get(&zoomed_id) {
-                    self.render_pane(stdout, pane, wm.tab_bar_height)?;
+                    self.render_pane(stdout, pane, wm.tab_bar_height, tab.synchronize_panes)?;
                 }
             }
         } else {
             for pane in tab.panes.values() {
-                self.render_pane(stdout, pane, wm.tab_bar_height)?;
+                self.render_pane(stdout, pane, wm.tab_bar_height, tab.synchronize_panes)?;
             }
         }
 
         Ok(())
     }
 
-    /// Render a single pane
-    fn render_pane<W: Write>(&self, stdout: &mut W, pane: &Pane, y_offset: u16) -> io::Result<()> {
+    /// Render a single pane; panes receiving synchronized input get a
+    /// double border
+    fn render_pane<W: Write>(&self, stdout: &mut W, pane: &Pane, y_offset: u16, synchronized: bool) -> io::Result<()> {
         let screen = pane.session.state.active_screen();
         let (inner_x, inner_y) = pane.inner_pos();
         let (inner_w, inner_h) = pane.inner_size();
@@ -907,7 +1025,7 @@ impl WmRenderer {
 
         // Draw border if needed
         if pane.border != BorderStyle::None {
-            self.render_border(stdout, pane, y_offset)?;
+            self.render_border(stdout, pane, y_offset, synchronized)?;
         }
 
         // Render content
@@ -982,16 +1100,19 @@ impl WmRenderer {
     }
 
     /// Render pane border
-    fn render_border<W: Write>(&self, stdout: &mut W, pane: &Pane, y_offset: u16) -> io::Result<()> {
+    fn render_border<W: Write>(&self, stdout: &mut W, pane: &Pane, y_offset: u16, synchronized: bool) -> io::Result<()> {
         let cs = &self.color_scheme;
-        let chars = BorderChars::single();
+        let chars = if synchronized { BorderChars::double() } else { BorderChars::single() };
         
-        // Border color based on focus
-        if pane.focused {
-            execute!(stdout, SetForegroundColor(cs.pane_border_active.to_crossterm()))?;
+        // Border color based on focus; the visual bell flashes it
+        let border_color = if pane.bell_flash.is_some() {
+            cs.status_prefix_bg
+        } else if pane.focused {
+            cs.pane_border_active
         } else {
-            execute!(stdout, SetForegroundColor(cs.pane_border.to_crossterm()))?;
-        }
+            cs.pane_border
+        };
+        execute!(stdout, SetForegroundColor(border_color.to_crossterm()))?;
 
         // Top border
         execute!(stdout, MoveTo(pane.x, y_offset + pane.y))?;
@@ -1000,13 +1121,18 @@ impl WmRenderer {
         // Title in top border
         let title = pane.display_title();
         let title_space = (pane.width as usize).saturating_sub(4);
-        let display_title: String = if title.len() > title_space {
-            title.chars().take(title_space).collect()
-        } else {
-            title
-        };
+        let mut display_title = String::new();
+        let mut title_width = 0;
+        for c in title.chars() {
+            let w = c.width().unwrap_or(0);
+            if title_width + w > title_space {
+                break;
+            }
+            display_title.push(c);
+            title_width += w;
+        }
         
-        let remaining = pane.width.saturating_sub(2 + display_title.len() as u16);
+        let remaining = pane.width.saturating_sub(2 + display_title.width() as u16);
         let left_pad = remaining / 2;
         let right_pad = remaining - left_pad;
         
@@ -1018,11 +1144,7 @@ impl WmRenderer {
             execute!(stdout, SetForegroundColor(cs.tab_active_fg.to_crossterm()))?;
         }
         write!(stdout, "{}", display_title)?;
-        if pane.focused {
-            execute!(stdout, SetForegroundColor(cs.pane_border_active.to_crossterm()))?;
-        } else {
-            execute!(stdout, SetForegroundColor(cs.pane_border.to_crossterm()))?;
-        }
+        execute!(stdout, SetForegroundColor(border_color.to_crossterm()))?;
         
         for _ in 0..right_pad {
             write!(stdout, "{}", chars.horizontal)?;
@@ -1062,7 +1184,10 @@ impl WmRenderer {
             SetForegroundColor(cs.status_bar_fg.to_crossterm())
         )?;
         
-        let status = wm.status_info();
+        let status = match wm.status_message() {
+            Some(message) => message.to_string(),
+            None => wm.status_info(),
+        };
         let prefix_name = wm.prefix_key.display_name();
         let shortcuts = if wm.prefix_mode {
             r#"c:new x:kill ":split %:vsplit n/p:win o:pane z:zoom t:theme"#.to_string()
@@ -27,11 +27,59 @@
 //! - tmux-compatible keybindings
 
 use std::collections::HashMap;
-use super::tab::{Tab, TabId};
-use super::pane::PaneId;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use super::tab::{Tab, TabAlerts, TabId};
+use super::pane::{Pane, PaneId};
 use super::layout::SplitDirection;
 
 use crate::config::PrefixKey;
+use crate::core::asciicast::{Player, Recorder, Recording};
+use crate::core::encoding::Encoding;
+use crate::core::pipe::{OutputPipe, PipeTarget};
+use crate::core::pty::{PtyError, Signal, SpawnSpec};
+use crate::core::session::OUTPUT_BYTES_PER_FRAME;
+use crate::event_loop::EventSender;
+
+/// How long a status bar message stays up
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// Which tabs' bells raise alerts (tmux `bell-action`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BellAction {
+    /// Bells in any tab
+    #[default]
+    Any,
+    /// Bells in the active tab only
+    Current,
+    /// Bells in tabs other than the active one
+    Other,
+    /// Ignore bells
+    None,
+}
+
+impl BellAction {
+    /// Parse a config value: `any`, `current`, `other` or `none`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "any" => Some(BellAction::Any),
+            "current" => Some(BellAction::Current),
+            "other" => Some(BellAction::Other),
+            "none" => Some(BellAction::None),
+            _ => None,
+        }
+    }
+
+    /// Whether a bell in a tab that is (or is not) active raises an alert
+    fn applies(self, active: bool) -> bool {
+        match self {
+            BellAction::Any => true,
+            BellAction::Current => active,
+            BellAction::Other => !active,
+            BellAction::None => false,
+        }
+    }
+}
 
 /// The central manager for all tabs and pane operations.
 ///
@@ -44,7 +92,7 @@ use crate::config::PrefixKey;
 /// # Example
 ///
 /// ```ignore
-/// let mut wm = WindowManager::new(80, 24, None, None);
+/// let mut wm = WindowManager::new(80, 24, SpawnSpec::default(), PrefixKey { char: 'b' });
 /// wm.start()?;  // Start the initial shell session
 ///
 /// // Create a new tab
@@ -71,19 +119,43 @@ pub struct WindowManager {
     pub tab_bar_height: u16,
     /// Height reserved for status bar
     pub status_bar_height: u16,
-    /// Default shell command
-    pub default_shell: Option<String>,
-    /// Default codepage
-    pub default_codepage: Option<u32>,
+    /// What new panes run unless told otherwise
+    pub default_spawn: SpawnSpec,
+    /// Keep panes whose process has exited (panes can override this)
+    pub remain_on_exit: bool,
+    /// Start new panes and tabs in the focused pane's working directory
+    pub inherit_cwd: bool,
+    /// Name tabs and pane borders after what runs in them
+    pub automatic_rename: bool,
+    /// How long `kill_pane` waits before forcing a process to exit
+    pub kill_timeout: Duration,
+    /// Flag background tabs whose panes produce output
+    pub monitor_activity: bool,
+    /// Flag background tabs whose panes produce no output for this long
+    pub monitor_silence: Option<Duration>,
+    /// Also report activity, silence and bell alerts in the status bar
+    pub alert_messages: bool,
+    /// Which tabs' bells raise alerts
+    pub bell_action: BellAction,
+    /// Flash the border of a pane that rings the bell
+    pub visual_bell: bool,
+    /// Pass bells on to the host terminal
+    pub audible_bell: bool,
+    /// Set when a bell is to be passed on to the host terminal
+    bell_pending: bool,
+    /// Message shown in the status bar, with when it was set
+    status_message: Option<(String, Instant)>,
     /// Prefix key mode (like tmux Ctrl+b)
     pub prefix_mode: bool,
     /// Configured prefix key
     pub prefix_key: PrefixKey,
+    /// Event loop that panes report output to
+    events: Option<EventSender>,
 }
 
 impl WindowManager {
     /// Create a new window manager
-    pub fn new(width: u16, height: u16, shell: Option<String>, codepage: Option<u32>, prefix_key: PrefixKey) -> Self {
+    pub fn new(width: u16, height: u16, default_spawn: SpawnSpec, prefix_key: PrefixKey) -> Self {
         let tab_bar_height = 1;
         let status_bar_height = 1;
         let content_height = height.saturating_sub(tab_bar_height + status_bar_height);
@@ -105,11 +177,33 @@ impl WindowManager {
             height,
             tab_bar_height,
             status_bar_height,
-            default_shell: shell,
-            default_codepage: codepage,
+            default_spawn,
+            remain_on_exit: false,
+            inherit_cwd: false,
+            automatic_rename: false,
+            kill_timeout: Duration::from_secs(3),
+            monitor_activity: false,
+            monitor_silence: None,
+            alert_messages: false,
+            bell_action: BellAction::default(),
+            visual_bell: false,
+            audible_bell: true,
+            bell_pending: false,
+            status_message: None,
             prefix_mode: false,
             prefix_key,
+            events: None,
+        }
+    }
+
+    /// Report output of all panes to an event loop.
+    ///
+    /// Call before `start` so the initial pane is covered as well.
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        for tab in self.tabs.values_mut() {
+            tab.set_event_sender(events.clone());
         }
+        self.events = Some(events);
     }
 
     /// Get content area dimensions (excluding tab bar and status bar)
@@ -117,21 +211,46 @@ impl WindowManager {
         (self.width, self.height.saturating_sub(self.tab_bar_height + self.status_bar_height))
     }
 
-    /// Create a new tab
+    /// Create a new tab running the default spawn spec
     pub fn new_tab(&mut self) -> TabId {
+        self.new_tab_with(self.new_pane_spec())
+    }
+
+    /// Spec for a new pane: the default, in the focused pane's directory
+    /// when `inherit_cwd` is set
+    fn new_pane_spec(&self) -> SpawnSpec {
+        let spec = self.default_spawn.clone();
+        if !self.inherit_cwd {
+            return spec;
+        }
+        // A directory on another host (e.g. over ssh) can't be entered here
+        match self.focused_cwd().filter(|cwd| cwd.is_dir()) {
+            Some(cwd) => spec.cwd(cwd),
+            None => spec,
+        }
+    }
+
+    /// Working directory of the focused pane
+    pub fn focused_cwd(&self) -> Option<PathBuf> {
+        self.active_tab()?.focused_pane()?.session.cwd()
+    }
+
+    /// Create a new tab whose initial pane runs `spec`
+    pub fn new_tab_with(&mut self, spec: SpawnSpec) -> TabId {
         let tab_id = self.next_tab_id;
         self.next_tab_id += 1;
         
         let (width, height) = self.content_size();
         let tab_name = format!("{}:shell", tab_id);
         let mut tab = Tab::new(tab_id, tab_name, width, height);
+        if let Some(events) = &self.events {
+            tab.set_event_sender(events.clone());
+        }
         
         // Start session in the initial pane
+        let spec = tab.pane_spawn_spec(tab.focused_pane, &spec);
         if let Some(pane) = tab.focused_pane_mut() {
-            let _ = pane.session.start_with_codepage(
-                self.default_shell.as_deref(),
-                self.default_codepage
-            );
+            let _ = pane.start(spec);
         }
         
         self.tabs.insert(tab_id, tab);
@@ -194,16 +313,14 @@ impl WindowManager {
 
     /// Split the current pane horizontally
     pub fn split_horizontal(&mut self) -> Option<PaneId> {
-        let shell = self.default_shell.clone();
-        let codepage = self.default_codepage;
-        self.active_tab_mut()?.split(SplitDirection::Horizontal, shell.as_deref(), codepage)
+        let spec = self.new_pane_spec();
+        self.active_tab_mut()?.split(SplitDirection::Horizontal, &spec)
     }
 
     /// Split the current pane vertically
     pub fn split_vertical(&mut self) -> Option<PaneId> {
-        let shell = self.default_shell.clone();
-        let codepage = self.default_codepage;
-        self.active_tab_mut()?.split(SplitDirection::Vertical, shell.as_deref(), codepage)
+        let spec = self.new_pane_spec();
+        self.active_tab_mut()?.split(SplitDirection::Vertical, &spec)
     }
 
     /// Close the current pane
@@ -257,10 +374,11 @@ impl WindowManager {
         }
     }
 
-    /// Rename the active tab
+    /// Rename the active tab, which stops automatic renaming for it
     pub fn rename_active_tab(&mut self, name: &str) {
         if let Some(tab) = self.active_tab_mut() {
             tab.name = name.to_string();
+            tab.automatic_rename = false;
         }
     }
 
@@ -344,20 +462,75 @@ impl WindowManager {
     }
 
     /// Process output for all tabs and handle closed panes
+    ///
+    /// Each pane processes at most about `OUTPUT_BYTES_PER_FRAME` per call, so
+    /// a flood of output in one pane doesn't hold up drawing and input.
     pub fn process_output(&mut self) -> bool {
-        let mut any_output = false;
+        let mut any_output = self.escalate_kills();
+        if self.status_message.as_ref().is_some_and(|(_, set)| set.elapsed() >= STATUS_MESSAGE_DURATION) {
+            self.status_message = None;
+            any_output = true;
+        }
         let tabs_to_check: Vec<TabId> = self.tabs.keys().cloned().collect();
+        let mut alerts = Vec::new();
         
         for tab_id in tabs_to_check.iter() {
             if let Some(tab) = self.tabs.get_mut(tab_id) {
-                if tab.process_output() {
+                let output = tab.process_output(OUTPUT_BYTES_PER_FRAME);
+                // A flash ending needs a redraw but is not output from the tab
+                if output || tab.expire_bell_flashes() {
+                    any_output = true;
+                }
+                let bells = tab.take_bells();
+                let active = *tab_id == self.active_tab;
+                if !bells.is_empty() && self.bell_action.applies(active) {
+                    self.bell_pending |= self.audible_bell;
+                    if active && self.visual_bell {
+                        let now = Instant::now();
+                        for pane_id in bells {
+                            if let Some(pane) = tab.panes.get_mut(&pane_id) {
+                                pane.bell_flash = Some(now);
+                            }
+                        }
+                        any_output = true;
+                    } else if !active && !tab.alerts.bell {
+                        tab.alerts.bell = true;
+                        alerts.push(format!("Bell in tab {}", tab.name));
+                    }
+                }
+                if active {
+                    // Alerts are for tabs out of view; silence counts from leaving
+                    if tab.alerts != TabAlerts::default() {
+                        tab.alerts = TabAlerts::default();
+                        any_output = true;
+                    }
+                    tab.reset_silence();
+                } else {
+                    if output && self.monitor_activity && !tab.alerts.activity {
+                        tab.alerts.activity = true;
+                        alerts.push(format!("Activity in tab {}", tab.name));
+                    }
+                    let silent = self.monitor_silence.is_some_and(|limit| tab.silent_for() >= limit);
+                    if silent && !tab.alerts.silence {
+                        tab.alerts.silence = true;
+                        alerts.push(format!("Silence in tab {}", tab.name));
+                    }
+                }
+                if self.automatic_rename && tab.update_automatic_names() {
                     any_output = true;
                 }
                 // Clean up dead panes
-                tab.cleanup_dead_panes();
+                tab.cleanup_dead_panes(self.remain_on_exit);
             }
         }
         
+        if !alerts.is_empty() {
+            any_output = true;
+            if self.alert_messages {
+                self.set_status_message(alerts.join(", "));
+            }
+        }
+
         // Remove empty tabs
         let empty_tabs: Vec<TabId> = self.tabs.iter()
             .filter(|(_, tab)| tab.panes.is_empty())
@@ -378,16 +551,333 @@ impl WindowManager {
         any_output
     }
 
+    /// Whether the focused pane's process has exited and the pane was kept
+    pub fn focused_pane_dead(&self) -> bool {
+        self.active_tab()
+            .and_then(|tab| tab.focused_pane())
+            .map(|pane| pane.dead)
+            .unwrap_or(false)
+    }
+
+    /// Command line the focused pane was started with
+    pub fn focused_command_line(&self) -> Option<String> {
+        let pane = self.active_tab()?.focused_pane()?;
+        Some(pane.spawn.as_ref()?.command_line())
+    }
+
+    /// Restart the focused pane's process, keeping its place in the layout
+    ///
+    /// `command` replaces the pane's command line; `None` runs it again.
+    pub fn respawn_pane(&mut self, command: Option<&str>, keep_scrollback: bool) -> Result<(), String> {
+        let default_spawn = self.default_spawn.clone();
+        let tab = self.active_tab_mut().ok_or("No active tab")?;
+        let pane_id = tab.focused_pane;
+        let spec = command.map(|command| {
+            tab.focused_pane()
+                .and_then(|pane| pane.spawn.clone())
+                .unwrap_or_else(|| tab.pane_spawn_spec(pane_id, &default_spawn))
+                .with_command_line(command)
+        });
+        tab.respawn_pane(pane_id, spec, keep_scrollback).map_err(|e| e.to_string())
+    }
+
+    /// Close the focused pane if it is dead, even if it is the last one
+    pub fn close_dead_pane(&mut self) {
+        if let Some(tab) = self.active_tab_mut() {
+            if tab.focused_pane().is_some_and(|pane| pane.dead) {
+                tab.remove_pane(tab.focused_pane);
+            }
+        }
+        // An emptied tab is closed along with it
+        self.process_output();
+    }
+
+    /// Start copying the focused pane's output to a file, or to a command
+    /// with `|command` (tmux: pipe-pane)
+    pub fn pipe_pane(&mut self, target: &str, strip_escapes: bool) -> Result<(), String> {
+        let target = PipeTarget::parse(target).ok_or("No file or command given")?;
+        let pane = self.active_tab()
+            .and_then(|tab| tab.focused_pane())
+            .ok_or("No active pane")?;
+        let pane_id = pane.id;
+        let result = OutputPipe::open(target.clone(), strip_escapes);
+        let message = match &result {
+            Ok(_) => format!("Piping pane {} to {}", pane_id, target),
+            Err(e) => format!("Pane {}: cannot pipe to {}: {}", pane_id, target, e),
+        };
+        let result = result
+            .map(|pipe| pane.session.set_output_pipe(Some(pipe)))
+            .map_err(|e| e.to_string());
+        self.set_status_message(message);
+        result
+    }
+
+    /// Stop copying the focused pane's output, returning whether it was piped
+    pub fn stop_pipe_pane(&mut self) -> bool {
+        let Some(pane) = self.active_tab().and_then(|tab| tab.focused_pane()) else {
+            return false;
+        };
+        let pane_id = pane.id;
+        match pane.session.take_output_pipe() {
+            Some(pipe) => {
+                let message = format!("Stopped piping pane {} to {}", pane_id, pipe.target());
+                self.set_status_message(message);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the focused pane's output is being piped
+    pub fn focused_pane_piped(&self) -> bool {
+        self.active_tab()
+            .and_then(|tab| tab.focused_pane())
+            .is_some_and(|pane| pane.session.output_pipe_target().is_some())
+    }
+
+    /// Start recording the focused pane to an asciicast v2 file
+    pub fn start_recording(&mut self, path: &str) -> Result<(), String> {
+        let path = path.trim();
+        if path.is_empty() {
+            return Err("No file given".to_string());
+        }
+        let pane = self.active_tab()
+            .and_then(|tab| tab.focused_pane())
+            .ok_or("No active pane")?;
+        let pane_id = pane.id;
+        let (cols, rows) = pane.inner_size();
+        let result = Recorder::create(path, cols, rows, pane.session.encoding(), Some(pane.display_title()));
+        let message = match &result {
+            Ok(_) => format!("Recording pane {} to {}", pane_id, path),
+            Err(e) => format!("Pane {}: cannot record to {}: {}", pane_id, path, e),
+        };
+        let result = result
+            .map(|recorder| pane.session.set_recorder(Some(recorder)))
+            .map_err(|e| e.to_string());
+        self.set_status_message(message);
+        result
+    }
+
+    /// Stop recording the focused pane, returning whether it was recorded
+    pub fn stop_recording(&mut self) -> bool {
+        let Some(pane) = self.active_tab().and_then(|tab| tab.focused_pane()) else {
+            return false;
+        };
+        let pane_id = pane.id;
+        match pane.session.take_recorder() {
+            Some(recorder) => {
+                let message = format!("Saved recording of pane {} to {}", pane_id, recorder.path().display());
+                self.set_status_message(message);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the focused pane is being recorded
+    pub fn focused_pane_recording(&self) -> bool {
+        self.active_tab()
+            .and_then(|tab| tab.focused_pane())
+            .is_some_and(|pane| pane.session.recording_path().is_some())
+    }
+
+    /// Open a new tab that plays back an asciicast recording
+    pub fn open_playback(&mut self, path: &str) -> Result<TabId, String> {
+        let path = path.trim();
+        let recording = match Recording::load(path) {
+            Ok(recording) => recording,
+            Err(e) => {
+                self.set_status_message(format!("Cannot play {}: {}", path, e));
+                return Err(e.to_string());
+            }
+        };
+
+        let tab_id = self.next_tab_id;
+        self.next_tab_id += 1;
+        let (width, height) = self.content_size();
+        let name = std::path::Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string());
+        let mut tab = Tab::new(tab_id, format!("{}:{}", tab_id, name), width, height);
+        if let Some(events) = &self.events {
+            tab.set_event_sender(events.clone());
+        }
+        if let Some(pane) = tab.focused_pane_mut() {
+            pane.title = Some(recording.title.clone().unwrap_or(name));
+            pane.start_playback(Player::new(recording));
+        }
+
+        self.tabs.insert(tab_id, tab);
+        self.tab_order.push(tab_id);
+        self.active_tab = tab_id;
+        Ok(tab_id)
+    }
+
+    /// The recording played back in the focused pane, if any
+    fn focused_player_mut(&mut self) -> Option<&mut Player> {
+        self.active_tab_mut()?.focused_pane_mut()?.playback.as_mut()
+    }
+
+    /// Whether the focused pane plays back a recording
+    pub fn focused_pane_playback(&self) -> bool {
+        self.active_tab()
+            .and_then(|tab| tab.focused_pane())
+            .is_some_and(|pane| pane.playback.is_some())
+    }
+
+    /// Pause or resume playback in the focused pane
+    pub fn toggle_playback_pause(&mut self) {
+        if let Some(player) = self.focused_player_mut() {
+            player.toggle_pause();
+        }
+    }
+
+    /// Multiply the playback speed of the focused pane by `factor`
+    pub fn change_playback_speed(&mut self, factor: f64) {
+        if let Some(player) = self.focused_player_mut() {
+            player.change_speed(factor);
+        }
+    }
+
+    /// Move playback in the focused pane by `delta` seconds
+    pub fn seek_playback(&mut self, delta: f64) {
+        if let Some(pane) = self.active_tab_mut().and_then(|tab| tab.focused_pane_mut()) {
+            pane.seek_playback(delta);
+        }
+        self.process_output();
+    }
+
+    /// Stop playback and close the focused pane
+    pub fn close_playback(&mut self) {
+        if let Some(tab) = self.active_tab_mut() {
+            if tab.focused_pane().is_some_and(|pane| pane.playback.is_some()) {
+                tab.remove_pane(tab.focused_pane);
+            }
+        }
+        self.process_output();
+    }
+
+    /// How long until a pane needs updating without output: a playback
+    /// event is due or the visual bell stops flashing
+    pub fn next_wakeup(&self) -> Option<Duration> {
+        self.tabs
+            .values()
+            .flat_map(|tab| tab.panes.values())
+            .filter_map(|pane| {
+                let due = pane.playback.as_ref().and_then(|player| player.next_due_in());
+                due.into_iter().chain(pane.bell_flash_remaining()).min()
+            })
+            .min()
+    }
+
+    /// Whether a bell is to be passed on to the host terminal; clears it
+    pub fn take_bell(&mut self) -> bool {
+        std::mem::take(&mut self.bell_pending)
+    }
+
+    /// Send a signal to the focused pane's processes, reporting the result
+    /// in the status bar
+    pub fn signal_pane(&mut self, signal: Signal) -> Result<(), PtyError> {
+        let pane = self.active_tab()
+            .and_then(|tab| tab.focused_pane())
+            .ok_or(PtyError::InvalidHandle)?;
+        let pane_id = pane.id;
+        let result = pane.session.signal(signal);
+        let message = match &result {
+            Ok(()) => format!("Sent {} to pane {}", signal.name(), pane_id),
+            Err(e) => format!("Pane {}: {}", pane_id, e),
+        };
+        self.set_status_message(message);
+        result
+    }
+
+    /// Kill the focused pane: ask its processes to exit, and force them to
+    /// if they are still running after `kill_timeout`.
+    ///
+    /// The pane closes once its process has exited.
+    pub fn kill_pane(&mut self) {
+        if self.focused_pane_dead() {
+            self.close_dead_pane();
+            return;
+        }
+        if self.signal_pane(Signal::Terminate).is_err() {
+            return;
+        }
+
+        let deadline = Instant::now() + self.kill_timeout;
+        if let Some(pane) = self.active_tab_mut().and_then(|tab| tab.focused_pane_mut()) {
+            pane.killed = true;
+            pane.kill_deadline = Some(deadline);
+        }
+    }
+
+    /// Force processes to exit whose kill-pane timeout has passed
+    ///
+    /// Returns whether anything was reported in the status bar.
+    fn escalate_kills(&mut self) -> bool {
+        let now = Instant::now();
+        let mut message = None;
+        for tab in self.tabs.values_mut() {
+            for pane in tab.panes.values_mut() {
+                if pane.kill_deadline.is_some_and(|deadline| deadline <= now) {
+                    pane.kill_deadline = None;
+                    if pane.session.signal(Signal::Kill).is_ok() {
+                        message = Some(format!(
+                            "Pane {} did not exit, sent {}",
+                            pane.id,
+                            Signal::Kill.name()
+                        ));
+                    }
+                }
+            }
+        }
+        match message {
+            Some(message) => {
+                self.set_status_message(message);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Show a message in the status bar for a few seconds
+    pub fn set_status_message(&mut self, message: impl Into<String>) {
+        self.status_message = Some((message.into(), Instant::now()));
+    }
+
+    /// Message currently shown in the status bar
+    pub fn status_message(&self) -> Option<&str> {
+        self.status_message.as_ref().map(|(message, _)| message.as_str())
+    }
+
+    /// Toggle remain-on-exit for the focused pane, returning the new setting
+    pub fn toggle_remain_on_exit(&mut self) -> Option<bool> {
+        let global = self.remain_on_exit;
+        let pane = self.active_tab_mut()?.focused_pane_mut()?;
+        let remain = !pane.remain_on_exit.unwrap_or(global);
+        pane.remain_on_exit = Some(remain);
+        Some(remain)
+    }
+
     /// Check if any tab is still running
     pub fn is_running(&self) -> bool {
         !self.tabs.is_empty() && self.tabs.values().any(|t| t.is_running())
     }
 
     /// Get tab info for rendering tab bar
+    ///
+    /// Names carry the markers of raised alerts.
     pub fn tab_info(&self) -> Vec<(TabId, String, bool)> {
         self.tab_order.iter().map(|&id| {
             let tab = self.tabs.get(&id).unwrap();
-            (id, tab.name.clone(), id == self.active_tab)
+            let marker = tab.alerts.marker();
+            let name = if marker.is_empty() {
+                tab.name.clone()
+            } else {
+                format!("{} {}", tab.name, marker)
+            };
+            (id, name, id == self.active_tab)
         }).collect()
     }
 
@@ -397,14 +887,29 @@ impl WindowManager {
             let pane_count = tab.panes.len();
             let focused_id = tab.focused_pane;
             let zoom_indicator = if tab.is_zoomed() { " [Z]" } else { "" };
+            let sync_indicator = if tab.synchronize_panes { " [SYNC]" } else { "" };
+            let pipe_indicator = if self.focused_pane_piped() { " [P]" } else { "" };
+            let rec_indicator = if self.focused_pane_recording() { " [REC]" } else { "" };
+            let play_indicator = tab.focused_pane()
+                .and_then(|pane| pane.playback.as_ref())
+                .map(|player| format!(" [{}]", player.status()))
+                .unwrap_or_default();
+            let encoding = tab.focused_pane()
+                .map(|pane| pane.session.encoding())
+                .unwrap_or_default();
             format!(
-                "[{}] {}:{} | Pane {}/{}{}",
+                "[{}] {}:{} | Pane {}/{}{}{}{}{}{} | {}",
                 self.active_tab,
                 tab.name,
                 focused_id,
                 focused_id,
                 pane_count,
-                zoom_indicator
+                zoom_indicator,
+                sync_indicator,
+                pipe_indicator,
+                rec_indicator,
+                play_indicator,
+                encoding.name()
             )
         } else {
             "No active tab".to_string()
@@ -567,21 +1072,46 @@ impl WindowManager {
 
     /// Start the initial session
     pub fn start(&mut self) -> Result<(), String> {
-        let shell = self.default_shell.clone();
-        let codepage = self.default_codepage;
+        let spec = self.default_spawn.clone();
         if let Some(tab) = self.active_tab_mut() {
+            let spec = tab.pane_spawn_spec(tab.focused_pane, &spec);
             if let Some(pane) = tab.focused_pane_mut() {
-                pane.session.start_with_codepage(
-                    shell.as_deref(),
-                    codepage
-                ).map_err(|e| e.to_string())?;
+                pane.start(spec).map_err(|e| e.to_string())?;
             }
         }
         Ok(())
     }
 
-    /// Write to the focused pane
+    /// Panes that input goes to: the focused pane, and with synchronized
+    /// panes every other pane in the tab that runs a process
+    fn input_panes(&mut self) -> Vec<&mut Pane> {
+        let Some(tab) = self.tabs.get_mut(&self.active_tab) else {
+            return Vec::new();
+        };
+        let focused = tab.focused_pane;
+        let synchronize = tab.synchronize_panes;
+        tab.panes
+            .values_mut()
+            .filter(|pane| {
+                pane.id == focused || (synchronize && !pane.dead && pane.playback.is_none())
+            })
+            .collect()
+    }
+
+    /// Write to the focused pane, or to all panes of the tab when they are
+    /// synchronized
     pub fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        let mut result = Ok(());
+        for pane in self.input_panes() {
+            if let Err(e) = pane.session.write(data) {
+                result = result.and(Err(e.to_string()));
+            }
+        }
+        result
+    }
+
+    /// Write to the focused pane only, e.g. for mouse reports
+    pub fn write_focused(&mut self, data: &[u8]) -> Result<(), String> {
         if let Some(tab) = self.active_tab_mut() {
             if let Some(pane) = tab.focused_pane_mut() {
                 pane.session.write(data).map_err(|e| e.to_string())?;
@@ -590,20 +1120,31 @@ impl WindowManager {
         Ok(())
     }
     
-    /// Paste text to the focused pane with bracketed paste support
+    /// Paste text with bracketed paste support, to the panes `write` uses
     pub fn paste(&mut self, text: &str) -> Result<(), String> {
-        let use_bracketed = self.tabs.get(&self.active_tab)
-            .and_then(|tab| tab.focused_pane())
-            .map(|pane| pane.session.state.modes.bracketed_paste)
-            .unwrap_or(false);
-        
-        let bytes = if use_bracketed {
-            format!("\x1b[200~{}\x1b[201~", text).into_bytes()
-        } else {
-            text.as_bytes().to_vec()
-        };
-        
-        self.write(&bytes)
+        let mut result = Ok(());
+        for pane in self.input_panes() {
+            let bytes = if pane.session.state.modes.bracketed_paste {
+                format!("\x1b[200~{}\x1b[201~", text).into_bytes()
+            } else {
+                text.as_bytes().to_vec()
+            };
+            if let Err(e) = pane.session.write(&bytes) {
+                result = result.and(Err(e.to_string()));
+            }
+        }
+        result
+    }
+
+    /// Toggle sending input to all panes of the active tab
+    pub fn toggle_synchronize_panes(&mut self) -> bool {
+        match self.active_tab_mut() {
+            Some(tab) => {
+                tab.synchronize_panes = !tab.synchronize_panes;
+                tab.synchronize_panes
+            }
+            None => false,
+        }
     }
     
     /// Paste from system clipboard to the focused pane
@@ -619,6 +1160,14 @@ impl WindowManager {
         Ok(())
     }
 
+    /// Switch the focused pane to the next encoding
+    pub fn cycle_encoding(&mut self) -> Option<Encoding> {
+        let session = &mut self.active_tab_mut()?.focused_pane_mut()?.session;
+        let encoding = session.encoding().next();
+        session.set_encoding(encoding);
+        Some(encoding)
+    }
+
     /// Toggle prefix mode
     #[allow(dead_code)]
     pub fn toggle_prefix_mode(&mut self) {
@@ -732,3 +1281,330 @@ impl WindowManager {
             })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pty::{wait_until, MockPty};
+    use std::sync::Arc;
+
+    /// Window manager whose initial pane runs on `mock`
+    fn mock_wm(mock: &MockPty) -> WindowManager {
+        let mut wm = WindowManager::new(80, 24, SpawnSpec::default(), PrefixKey { char: 'b' });
+        wm.active_tab_mut()
+            .and_then(|tab| tab.focused_pane_mut())
+            .unwrap()
+            .session
+            .start_with_pty(Arc::new(mock.clone()));
+        wm
+    }
+
+    #[test]
+    fn test_paste_plain() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+
+        wm.paste("echo hi").unwrap();
+        assert!(wait_until(|| mock.written_string() == "echo hi"));
+    }
+
+    #[test]
+    fn test_paste_bracketed() {
+        // The application enables bracketed paste mode
+        let mock = MockPty::with_output(b"\x1b[?2004h");
+        let mut wm = mock_wm(&mock);
+
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.active_tab()
+                .and_then(|tab| tab.focused_pane())
+                .map(|pane| pane.session.state.modes.bracketed_paste)
+                .unwrap_or(false)
+        }));
+
+        wm.paste("echo hi").unwrap();
+        assert!(wait_until(|| mock.written_string() == "\x1b[200~echo hi\x1b[201~"));
+    }
+
+    #[test]
+    fn test_paste_into_echoing_pane() {
+        // Echoes more output than the session buffers, so the paste only
+        // completes while the main loop keeps reading output
+        let mock = MockPty::echoing();
+        let mut wm = mock_wm(&mock);
+        let text = "x".repeat(200 * 1024);
+
+        wm.paste(&text).unwrap();
+        assert!(wait_until(|| {
+            wm.process_output();
+            mock.written().len() == text.len()
+        }));
+    }
+
+    #[test]
+    fn test_write_to_focused_pane() {
+        let first = MockPty::new();
+        let second = MockPty::new();
+        let mut wm = mock_wm(&first);
+        wm.active_tab_mut().unwrap().split_with(SplitDirection::Horizontal, |session| {
+            session.start_with_pty(Arc::new(second.clone()));
+            Ok(())
+        });
+
+        wm.write(b"a").unwrap();
+        wm.focus_next_pane();
+        wm.write(b"b").unwrap();
+        assert!(wait_until(|| second.written() == b"a" && first.written() == b"b"));
+    }
+
+    #[test]
+    fn test_cycle_encoding() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+        assert!(wm.status_info().ends_with("| UTF-8"));
+
+        assert_eq!(wm.cycle_encoding(), Some(Encoding::Cp932));
+        assert!(wm.status_info().ends_with("| Shift-JIS"));
+
+        wm.write("日".as_bytes()).unwrap();
+        assert!(wait_until(|| mock.written() == b"\x93\xfa"));
+    }
+
+    #[test]
+    fn test_process_output_removes_exited_tab() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+        assert!(wm.is_running());
+
+        mock.exit(0);
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.active_tab().is_none()
+        }));
+        assert!(!wm.is_running());
+        assert!(wm.tab_info().is_empty());
+    }
+
+    /// `file://` URL for `path` as a shell sends it in OSC 7, such as
+    /// `file://host/C:/Users/me` for a Windows path
+    fn file_url(path: &std::path::Path) -> String {
+        let path = path.to_string_lossy().replace('\\', "/");
+        let mut url = String::from("file://host");
+        if !path.starts_with('/') {
+            url.push('/');
+        }
+        for b in path.bytes() {
+            if b.is_ascii_alphanumeric() || b"/:-._~".contains(&b) {
+                url.push(b as char);
+            } else {
+                url.push_str(&format!("%{:02X}", b));
+            }
+        }
+        url
+    }
+
+    #[test]
+    fn test_new_pane_spec_inherits_cwd() {
+        let dir = std::env::temp_dir();
+        let mock = MockPty::with_output(format!("\x1b]7;{}\x07", file_url(&dir)).as_bytes());
+        let mut wm = mock_wm(&mock);
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.focused_cwd().is_some()
+        }));
+        assert_eq!(wm.focused_cwd(), Some(dir.clone()));
+        assert_eq!(wm.new_pane_spec().cwd, None);
+
+        wm.inherit_cwd = true;
+        assert_eq!(wm.new_pane_spec().cwd, Some(dir));
+    }
+
+    #[test]
+    fn test_remain_on_exit_last_pane() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+        wm.remain_on_exit = true;
+
+        mock.exit(1);
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.focused_pane_dead()
+        }));
+        assert!(wm.is_running());
+
+        wm.close_dead_pane();
+        assert!(!wm.is_running());
+        assert!(wm.tab_info().is_empty());
+    }
+
+    #[test]
+    fn test_playback_pane() {
+        let path = std::env::temp_dir().join(format!("wtmux-play-{}.cast", std::process::id()));
+        std::fs::write(
+            &path,
+            "{\"version\": 2, \"width\": 20, \"height\": 5}\n[0, \"o\", \"hello\"]\n[100, \"o\", \"\\r\\nlater\"]\n",
+        )
+        .unwrap();
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+
+        wm.open_playback(path.to_str().unwrap()).unwrap();
+        let row = |wm: &WindowManager, row: usize| {
+            let screen = wm.active_tab().unwrap().focused_pane().unwrap().session.state.active_screen();
+            (0..20).filter_map(|col| screen.get_char_at(row, col)).collect::<String>().trim_end().to_string()
+        };
+        wm.process_output();
+        assert!(wm.focused_pane_playback());
+        assert_eq!(row(&wm, 0), "hello");
+        assert_eq!(row(&wm, 1), "");
+
+        wm.seek_playback(200.0);
+        assert_eq!(row(&wm, 1), "later");
+
+        // Seeking back replays into a fresh terminal
+        wm.seek_playback(-50.0);
+        assert_eq!(row(&wm, 0), "hello");
+        assert_eq!(row(&wm, 1), "");
+
+        wm.close_playback();
+        assert_eq!(wm.tab_info().len(), 1);
+        assert!(!wm.focused_pane_playback());
+
+        // Closing removes the pane even with remain-on-exit
+        wm.remain_on_exit = true;
+        wm.open_playback(path.to_str().unwrap()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        wm.process_output();
+        wm.close_playback();
+        assert_eq!(wm.tab_info().len(), 1);
+        assert!(!wm.active_tab().unwrap().focused_pane().unwrap().dead);
+    }
+
+    #[test]
+    fn test_monitor_activity_and_silence() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+        wm.monitor_activity = true;
+        wm.monitor_silence = Some(Duration::ZERO);
+        wm.alert_messages = true;
+
+        // A second tab in front of the mock's
+        let background = MockPty::new();
+        let mut tab = Tab::new(2, "2:build".to_string(), 80, 22);
+        tab.focused_pane_mut().unwrap().session.start_with_pty(Arc::new(background.clone()));
+        wm.tabs.insert(2, tab);
+        wm.tab_order.push(2);
+        wm.goto_tab(2);
+        wm.process_output();
+        assert_eq!(wm.tab_info()[0].1, "1:main ~");
+        assert_eq!(wm.status_message(), Some("Silence in tab 1:main"));
+
+        mock.push_output(b"done\r\n");
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.tabs[&1].alerts.activity
+        }));
+        assert_eq!(wm.tab_info()[0].1, "1:main #~");
+        assert_eq!(wm.tab_info()[1].1, "2:build");
+
+        // Visiting the tab clears its alerts
+        wm.goto_tab(1);
+        wm.process_output();
+        assert_eq!(wm.tab_info()[0].1, "1:main");
+    }
+
+    #[test]
+    fn test_bell_action() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+        wm.visual_bell = true;
+
+        let background = MockPty::new();
+        let mut tab = Tab::new(2, "2:build".to_string(), 80, 22);
+        tab.focused_pane_mut().unwrap().session.start_with_pty(Arc::new(background.clone()));
+        wm.tabs.insert(2, tab);
+        wm.tab_order.push(2);
+
+        // A bell in the active tab flashes the pane
+        mock.push_output(b"\x07");
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.take_bell()
+        }));
+        assert!(wm.tabs[&1].panes[&1].bell_flash.is_some());
+
+        // The flash ending is not activity in the tab
+        wm.monitor_activity = true;
+        wm.goto_tab(2);
+        std::thread::sleep(Duration::from_millis(250));
+        assert!(wm.process_output());
+        assert!(wm.tabs[&1].panes[&1].bell_flash.is_none());
+        assert!(!wm.tabs[&1].alerts.activity);
+        wm.monitor_activity = false;
+        wm.goto_tab(1);
+
+        // Bells elsewhere are ignored with bell-action current
+        wm.bell_action = BellAction::Current;
+        background.push_output(b"x\x07");
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.tabs[&2].panes[&1].session.state.active_screen().get_char_at(0, 0) == Some('x')
+        }));
+        assert!(!wm.take_bell());
+        assert_eq!(wm.tab_info()[1].1, "2:build");
+
+        // and flag the tab with bell-action other
+        wm.bell_action = BellAction::Other;
+        background.push_output(b"\x07");
+        assert!(wait_until(|| {
+            wm.process_output();
+            wm.take_bell()
+        }));
+        assert_eq!(wm.tab_info()[1].1, "2:build !");
+    }
+
+    #[test]
+    fn test_synchronize_panes() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+        let other = MockPty::new();
+        let mut pane = Pane::new(2, 40, 10);
+        pane.session.start_with_pty(Arc::new(other.clone()));
+        wm.active_tab_mut().unwrap().panes.insert(2, pane);
+
+        wm.write(b"a").unwrap();
+        assert!(wm.toggle_synchronize_panes());
+        assert!(wm.status_info().contains("[SYNC]"));
+        wm.write(b"b").unwrap();
+        wm.paste("c").unwrap();
+        // Mouse reports stay with the focused pane
+        wm.write_focused(b"d").unwrap();
+
+        assert!(wait_until(|| mock.written_string() == "abcd"));
+        assert!(wait_until(|| other.written_string() == "bc"));
+    }
+
+    #[test]
+    fn test_kill_pane_escalates() {
+        let mock = MockPty::new();
+        let mut wm = mock_wm(&mock);
+        wm.remain_on_exit = true;
+        wm.kill_timeout = Duration::ZERO;
+
+        wm.kill_pane();
+        assert_eq!(mock.signals(), vec![Signal::Terminate]);
+        let sent = format!("Sent {} to pane 1", Signal::Terminate.name());
+        assert_eq!(wm.status_message(), Some(sent.as_str()));
+
+        // The process ignored it, so the next pass forces it to exit
+        wm.process_output();
+        assert_eq!(mock.signals(), vec![Signal::Terminate, Signal::Kill]);
+
+        // A killed pane closes even with remain-on-exit
+        mock.exit(137);
+        assert!(wait_until(|| {
+            wm.process_output();
+            !wm.is_running()
+        }));
+    }
+}
@@ -25,4 +25,4 @@ pub mod manager;
 
 pub use pane::{Pane, PaneId, BorderStyle};
 pub use layout::SplitDirection;
-pub use manager::WindowManager;
+pub use manager::{BellAction, WindowManager};
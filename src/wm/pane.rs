@@ -1,10 +1,20 @@
 //! Pane - A single terminal pane within a tab
 
+use std::time::{Duration, Instant};
+
+use crate::core::asciicast::{EventKind, Player};
+use crate::core::pty::{PtyError, SpawnSpec};
 use crate::core::session::Session;
 
 /// Unique identifier for a pane
 pub type PaneId = u64;
 
+/// How long the visual bell flashes a pane's border
+const VISUAL_BELL_DURATION: Duration = Duration::from_millis(200);
+
+/// How often the foreground process is looked up for automatic renaming
+const FOREGROUND_NAME_INTERVAL: Duration = Duration::from_secs(1);
+
 /// A single pane containing a terminal session
 pub struct Pane {
     /// Unique identifier
@@ -23,24 +33,38 @@ pub struct Pane {
     pub border: BorderStyle,
     /// Title (optional override)
     pub title: Option<String>,
+    /// Title from automatic renaming, used when there is no override
+    pub auto_title: Option<String>,
+    /// Foreground command name and when it was looked up
+    foreground_name: Option<(Instant, Option<String>)>,
+    /// What the pane runs, kept for respawning
+    pub spawn: Option<SpawnSpec>,
+    /// Keep the pane after its process exits (`None` follows the global option)
+    pub remain_on_exit: Option<bool>,
+    /// Set once the process has exited and the pane was kept
+    pub dead: bool,
+    /// When a pending kill-pane forces the process to exit
+    pub kill_deadline: Option<Instant>,
+    /// Set by kill-pane: the pane closes once its process exits, even with
+    /// remain-on-exit
+    pub killed: bool,
+    /// Recording played back in this pane instead of running a process
+    pub playback: Option<Player>,
+    /// When the visual bell started flashing the border
+    pub bell_flash: Option<Instant>,
 }
 
 /// Border drawing style
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, PartialEq, Default)]
 #[allow(dead_code)]
 pub enum BorderStyle {
     None,
+    #[default]
     Single,
     Double,
     Rounded,
 }
 
-impl Default for BorderStyle {
-    fn default() -> Self {
-        BorderStyle::Single
-    }
-}
-
 impl Pane {
     /// Create a new pane with border (default)
     pub fn new(id: PaneId, cols: u16, rows: u16) -> Self {
@@ -59,6 +83,15 @@ impl Pane {
             focused: false,
             border: BorderStyle::default(),
             title: None,
+            auto_title: None,
+            foreground_name: None,
+            spawn: None,
+            remain_on_exit: None,
+            dead: false,
+            kill_deadline: None,
+            killed: false,
+            playback: None,
+            bell_flash: None,
         }
     }
     
@@ -74,6 +107,76 @@ impl Pane {
             focused: false,
             border: BorderStyle::None,
             title: None,
+            auto_title: None,
+            foreground_name: None,
+            spawn: None,
+            remain_on_exit: None,
+            dead: false,
+            kill_deadline: None,
+            killed: false,
+            playback: None,
+            bell_flash: None,
+        }
+    }
+
+    /// Start the pane's process, remembering the spec for respawning
+    pub fn start(&mut self, spec: SpawnSpec) -> Result<(), PtyError> {
+        self.session.start(&spec)?;
+        self.title = spec.title.clone();
+        self.spawn = Some(spec);
+        Ok(())
+    }
+
+    /// Mark the pane dead and print the exit status below its output
+    pub fn mark_dead(&mut self) {
+        let status = match self.session.exit_code() {
+            Some(code) => format!("[exited with code {}]", code),
+            None => "[exited]".to_string(),
+        };
+        self.session.feed_bytes(format!("\x1b[0m\r\n{}", status).as_bytes());
+        self.dead = true;
+    }
+
+    /// Time left until the visual bell stops flashing, if it is
+    pub fn bell_flash_remaining(&self) -> Option<Duration> {
+        self.bell_flash
+            .map(|start| VISUAL_BELL_DURATION.saturating_sub(start.elapsed()))
+    }
+
+    /// Play back a recording in this pane, in a terminal of the recording's size
+    pub fn start_playback(&mut self, player: Player) {
+        let (cols, rows) = player.size();
+        self.session = Session::new(self.id, cols, rows);
+        self.playback = Some(player);
+    }
+
+    /// Feed the recording's events that are due into the terminal
+    ///
+    /// Returns whether anything was played.
+    pub fn advance_playback(&mut self) -> bool {
+        let Some(player) = &mut self.playback else {
+            return false;
+        };
+        let events = player.take_due();
+        for event in events {
+            match &event.kind {
+                EventKind::Output(text) => self.session.feed_bytes(text.as_bytes()),
+                EventKind::Resize(cols, rows) => {
+                    let _ = self.session.resize(*cols, *rows);
+                }
+            }
+        }
+        !events.is_empty()
+    }
+
+    /// Move playback by `delta` seconds
+    pub fn seek_playback(&mut self, delta: f64) {
+        if let Some(player) = &mut self.playback {
+            if player.seek(player.time() + delta) {
+                // Play the events up to there again in a clean terminal
+                let (cols, rows) = player.size();
+                self.session = Session::new(self.id, cols, rows);
+            }
         }
     }
 
@@ -110,6 +213,10 @@ impl Pane {
     pub fn resize(&mut self, width: u16, height: u16) {
         self.width = width;
         self.height = height;
+        if self.playback.is_some() {
+            // Played back at the recorded size, cropped or padded to fit
+            return;
+        }
         let (inner_w, inner_h) = self.inner_size();
         if let Err(e) = self.session.resize(inner_w, inner_h) {
             eprintln!(
@@ -127,10 +234,36 @@ impl Pane {
 
     /// Get display title
     pub fn display_title(&self) -> String {
-        if let Some(ref title) = self.title {
+        let title = if let Some(ref title) = self.title {
+            title.clone()
+        } else if let Some(ref title) = self.auto_title {
             title.clone()
         } else {
             format!("Pane {}", self.id)
+        };
+        match &self.playback {
+            Some(player) => format!("{} [{}]", title, player.status()),
+            None => title,
+        }
+    }
+
+    /// Name for automatic renaming: the title set by the program (OSC 0/2),
+    /// else the foreground process's command name
+    ///
+    /// The command name is looked up at most once per
+    /// `FOREGROUND_NAME_INTERVAL`, as this runs on every output pass.
+    pub fn automatic_title(&mut self) -> Option<String> {
+        let title = self.session.state.title.trim();
+        if !title.is_empty() {
+            return Some(title.to_string());
+        }
+        match &self.foreground_name {
+            Some((checked, name)) if checked.elapsed() < FOREGROUND_NAME_INTERVAL => name.clone(),
+            _ => {
+                let name = self.session.foreground_name();
+                self.foreground_name = Some((Instant::now(), name.clone()));
+                name
+            }
         }
     }
 
@@ -1,12 +1,49 @@
 //! Tab - A container for panes with a layout
 
 use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthChar;
+use crate::core::pty::{PtyError, SpawnSpec};
+use crate::core::session::Session;
+use crate::event_loop::EventSender;
 use super::pane::{Pane, PaneId, BorderStyle};
 use super::layout::{Layout, LayoutType, SplitDirection};
 
 /// Unique identifier for a tab
 pub type TabId = u64;
 
+/// Longest automatic tab name, in columns
+const AUTO_NAME_MAX_WIDTH: usize = 24;
+
+/// Alerts raised in a tab while it is not the active one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TabAlerts {
+    /// A pane produced output (monitor-activity)
+    pub activity: bool,
+    /// No pane produced output for a while (monitor-silence)
+    pub silence: bool,
+    /// A pane rang the bell
+    pub bell: bool,
+}
+
+impl TabAlerts {
+    /// Tab bar marker for the raised alerts, as in tmux: `!` for a bell,
+    /// `#` for activity and `~` for silence
+    pub fn marker(&self) -> String {
+        let mut marker = String::new();
+        if self.bell {
+            marker.push('!');
+        }
+        if self.activity {
+            marker.push('#');
+        }
+        if self.silence {
+            marker.push('~');
+        }
+        marker
+    }
+}
+
 /// Reason for reflow (used for debugging and optimization)
 #[derive(Debug, Clone, Copy)]
 pub enum ReflowReason {
@@ -21,10 +58,17 @@ pub enum ReflowReason {
 /// A tab containing multiple panes
 pub struct Tab {
     /// Unique identifier
-    #[allow(dead_code)]
     pub id: TabId,
     /// Tab name
     pub name: String,
+    /// Name the tab after its focused pane; cleared by a manual rename
+    pub automatic_rename: bool,
+    /// Send input to every pane instead of only the focused one
+    pub synchronize_panes: bool,
+    /// Alerts raised since the tab was last active
+    pub alerts: TabAlerts,
+    /// When a pane last produced output
+    last_output: Instant,
     /// Layout tree
     pub layout: Layout,
     /// All panes in this tab
@@ -44,6 +88,8 @@ pub struct Tab {
     current_layout: LayoutType,
     /// Layout generation (incremented on each reflow)
     pub layout_generation: u64,
+    /// Event loop that new panes report output to
+    events: Option<EventSender>,
 }
 
 impl Tab {
@@ -59,6 +105,10 @@ impl Tab {
         Self {
             id,
             name,
+            automatic_rename: true,
+            synchronize_panes: false,
+            alerts: TabAlerts::default(),
+            last_output: Instant::now(),
             layout: Layout::new(pane_id),
             panes,
             pane_order: vec![pane_id],
@@ -69,11 +119,42 @@ impl Tab {
             zoomed_pane: None,
             current_layout: LayoutType::Custom,
             layout_generation: 0,
+            events: None,
         }
     }
 
-    /// Split the current pane
-    pub fn split(&mut self, direction: SplitDirection, shell_cmd: Option<&str>, codepage: Option<u32>) -> Option<PaneId> {
+    /// Report output of this tab's panes to an event loop.
+    ///
+    /// Applies to panes that have not been started yet and to later splits.
+    pub fn set_event_sender(&mut self, events: EventSender) {
+        for pane in self.panes.values_mut() {
+            pane.session.set_event_sender(events.clone());
+        }
+        self.events = Some(events);
+    }
+
+    /// `spec` as run in pane `pane_id` of this tab, with the pane's identity
+    pub fn pane_spawn_spec(&self, pane_id: PaneId, spec: &SpawnSpec) -> SpawnSpec {
+        spec.clone().pane_identity(self.id, pane_id)
+    }
+
+    /// Split the current pane, running `spec` in the new pane
+    pub fn split(&mut self, direction: SplitDirection, spec: &SpawnSpec) -> Option<PaneId> {
+        // split_with gives the new pane the next pane ID
+        let spec = self.pane_spawn_spec(self.next_pane_id, spec);
+        let pane_id = self.split_with(direction, |session| session.start(&spec))?;
+        if let Some(pane) = self.panes.get_mut(&pane_id) {
+            pane.title = spec.title.clone();
+            pane.spawn = Some(spec);
+        }
+        Some(pane_id)
+    }
+
+    /// Split the current pane, starting the new pane's session with `start`
+    pub fn split_with<F>(&mut self, direction: SplitDirection, start: F) -> Option<PaneId>
+    where
+        F: FnOnce(&mut Session) -> Result<(), PtyError>,
+    {
         // Unzoom if zoomed
         self.zoomed_pane = None;
         
@@ -97,9 +178,12 @@ impl Tab {
         // Create pane with Single border (will be confirmed by reflow)
         let mut new_pane = Pane::new(new_pane_id, *new_width, *new_height);
         new_pane.border = BorderStyle::Single;
+        if let Some(events) = &self.events {
+            new_pane.session.set_event_sender(events.clone());
+        }
         
         // Start the session
-        if let Err(e) = new_pane.session.start_with_codepage(shell_cmd, codepage) {
+        if let Err(e) = start(&mut new_pane.session) {
             eprintln!("Failed to start pane session: {}", e);
             return None;
         }
@@ -121,36 +205,52 @@ impl Tab {
         if self.panes.len() <= 1 {
             return false; // Can't close the last pane
         }
-        
-        let pane_id = self.focused_pane;
-        
-        // Unzoom if zoomed pane was closed
-        if self.zoomed_pane == Some(pane_id) {
-            self.zoomed_pane = None;
+        self.remove_pane(self.focused_pane)
+    }
+
+    /// Remove a pane, even the last one, leaving the tab empty
+    ///
+    /// Returns whether the pane was found.
+    pub fn remove_pane(&mut self, pane_id: PaneId) -> bool {
+        if !self.detach_pane(pane_id) {
+            return false;
+        }
+        self.after_panes_removed();
+        true
+    }
+
+    /// Take a pane out of the layout and pane list, without refocusing or
+    /// reflowing
+    fn detach_pane(&mut self, pane_id: PaneId) -> bool {
+        if self.panes.remove(&pane_id).is_none() {
+            return false;
         }
-        
-        // Remove from layout
         if let Some(new_layout) = self.layout.remove(pane_id) {
             self.layout = new_layout;
-        } else {
-            return false;
         }
-        
-        // Remove pane
-        self.panes.remove(&pane_id);
         self.pane_order.retain(|&id| id != pane_id);
-        
-        // Focus another pane
-        if let Some(&new_focus) = self.panes.keys().next() {
-            self.focus_pane(new_focus);
+
+        // Unzoom if zoomed pane was closed
+        if self.zoomed_pane == Some(pane_id) {
+            self.zoomed_pane = None;
         }
-        
-        // Reflow handles all geometry and border changes
-        self.reflow(ReflowReason::Close);
-        
         true
     }
 
+    /// Move focus off removed panes and lay out the remaining ones
+    fn after_panes_removed(&mut self) {
+        if !self.panes.contains_key(&self.focused_pane) {
+            if let Some(&new_focus) = self.panes.keys().next() {
+                self.focus_pane(new_focus);
+            }
+        }
+
+        // Single reflow handles all geometry and border changes
+        if !self.panes.is_empty() {
+            self.reflow(ReflowReason::Close);
+        }
+    }
+
     /// Focus a specific pane
     pub fn focus_pane(&mut self, pane_id: PaneId) {
         // Check if zoom target will change
@@ -257,27 +357,106 @@ impl Tab {
         None
     }
 
-    /// Process output for all panes
-    pub fn process_output(&mut self) -> bool {
+    /// Process output for all panes, about `max_bytes` at most per pane
+    pub fn process_output(&mut self, max_bytes: usize) -> bool {
         let mut any_output = false;
         for pane in self.panes.values_mut() {
-            if pane.session.process_output().unwrap_or(false) {
+            if pane.session.process_output(max_bytes).unwrap_or(false) {
                 any_output = true;
             }
+            if pane.advance_playback() {
+                any_output = true;
+            }
+        }
+        if any_output {
+            self.last_output = Instant::now();
         }
         any_output
     }
 
-    /// Check if any pane is still running
+    /// Stop the visual bell on panes whose flash is over, returning
+    /// whether any needs redrawing
+    pub fn expire_bell_flashes(&mut self) -> bool {
+        let mut expired = false;
+        for pane in self.panes.values_mut() {
+            if pane.bell_flash_remaining() == Some(Duration::ZERO) {
+                pane.bell_flash = None;
+                expired = true;
+            }
+        }
+        expired
+    }
+
+    /// Panes that rang the bell since the last call
+    pub fn take_bells(&mut self) -> Vec<PaneId> {
+        self.panes
+            .values_mut()
+            .filter_map(|pane| std::mem::take(&mut pane.session.state.bell).then_some(pane.id))
+            .collect()
+    }
+
+    /// How long no pane has produced output
+    pub fn silent_for(&self) -> Duration {
+        self.last_output.elapsed()
+    }
+
+    /// Restart the silence timer, as if a pane had produced output
+    pub fn reset_silence(&mut self) {
+        self.last_output = Instant::now();
+    }
+
+    /// Update automatic pane titles and, unless renamed by hand, the tab name
+    ///
+    /// Returns whether anything changed.
+    pub fn update_automatic_names(&mut self) -> bool {
+        let mut changed = false;
+        for pane in self.panes.values_mut() {
+            let title = pane.automatic_title();
+            if title.is_some() && title != pane.auto_title {
+                pane.auto_title = title;
+                changed = true;
+            }
+        }
+
+        if self.automatic_rename {
+            let title = self.focused_pane().and_then(|pane| pane.auto_title.as_deref());
+            if let Some(title) = title {
+                let name = format!("{}:{}", self.id, truncate_to_width(title, AUTO_NAME_MAX_WIDTH));
+                if name != self.name {
+                    self.name = name;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Check if any pane is still running, kept after its process exited,
+    /// or playing back a recording
     pub fn is_running(&self) -> bool {
-        self.panes.values().any(|p| p.session.is_running())
+        self.panes.values().any(|p| p.session.is_running() || p.dead || p.playback.is_some())
     }
 
     /// Clean up dead panes (where shell has exited)
-    pub fn cleanup_dead_panes(&mut self) {
+    ///
+    /// Panes with remain-on-exit (their own setting, else `remain_on_exit`)
+    /// are kept and show the exit status instead. Playback panes have no
+    /// process and stay until their playback is closed.
+    pub fn cleanup_dead_panes(&mut self, remain_on_exit: bool) {
+        for pane in self.panes.values_mut() {
+            if !pane.session.is_running()
+                && !pane.dead
+                && !pane.killed
+                && pane.playback.is_none()
+                && pane.remain_on_exit.unwrap_or(remain_on_exit)
+            {
+                pane.mark_dead();
+            }
+        }
+
         let dead_panes: Vec<PaneId> = self.panes
             .iter()
-            .filter(|(_, pane)| !pane.session.is_running())
+            .filter(|(_, pane)| !pane.session.is_running() && !pane.dead && pane.playback.is_none())
             .map(|(id, _)| *id)
             .collect();
         
@@ -286,30 +465,64 @@ impl Tab {
         }
         
         for pane_id in dead_panes {
-            // Remove from layout
-            if let Some(new_layout) = self.layout.remove(pane_id) {
-                self.layout = new_layout;
-            }
-            self.panes.remove(&pane_id);
-            self.pane_order.retain(|&id| id != pane_id);
-            
-            // Unzoom if zoomed pane was closed
-            if self.zoomed_pane == Some(pane_id) {
-                self.zoomed_pane = None;
-            }
+            self.detach_pane(pane_id);
         }
-        
-        // Update focus if needed
-        if !self.panes.contains_key(&self.focused_pane) {
-            if let Some(&new_focus) = self.panes.keys().next() {
-                self.focus_pane(new_focus);
-            }
+        self.after_panes_removed();
+    }
+
+    /// Restart a pane's process
+    ///
+    /// Runs `spec`, or the spec the pane was last started with. With
+    /// `keep_scrollback` the old output stays reachable by scrolling up.
+    pub fn respawn_pane(
+        &mut self,
+        pane_id: PaneId,
+        spec: Option<SpawnSpec>,
+        keep_scrollback: bool,
+    ) -> Result<(), PtyError> {
+        let spec = spec
+            .or_else(|| self.panes.get(&pane_id)?.spawn.clone())
+            .ok_or(PtyError::InvalidHandle)?;
+        self.respawn_pane_with(pane_id, keep_scrollback, |session| session.start(&spec))?;
+        if let Some(pane) = self.panes.get_mut(&pane_id) {
+            pane.spawn = Some(spec);
         }
-        
-        // Single reflow handles all geometry and border changes
-        if !self.panes.is_empty() {
-            self.reflow(ReflowReason::Close);
+        Ok(())
+    }
+
+    /// Replace a pane's session with a new one started by `start`
+    ///
+    /// The pane keeps its ID and place in the layout; the old process, if
+    /// still running, is hung up.
+    pub fn respawn_pane_with<F>(&mut self, pane_id: PaneId, keep_scrollback: bool, start: F) -> Result<(), PtyError>
+    where
+        F: FnOnce(&mut Session) -> Result<(), PtyError>,
+    {
+        let events = self.events.clone();
+        let pane = self.panes.get_mut(&pane_id).ok_or(PtyError::InvalidHandle)?;
+
+        let (cols, rows) = pane.inner_size();
+        let mut session = Session::new(pane_id, cols, rows);
+        if let Some(events) = events {
+            session.set_event_sender(events);
+        }
+        start(&mut session)?;
+
+        if keep_scrollback {
+            let screen = &mut session.state.primary_screen;
+            for row in pane.session.state.take_history() {
+                screen.push_to_scrollback(row);
+            }
         }
+
+        // Keep piping and recording the pane's output across the restart
+        session.set_output_pipe(pane.session.take_output_pipe());
+        session.set_recorder(pane.session.take_recorder());
+        pane.session = session;
+        pane.dead = false;
+        pane.kill_deadline = None;
+        pane.killed = false;
+        Ok(())
     }
 
     /// Toggle zoom on focused pane
@@ -432,3 +645,265 @@ impl Tab {
         self.reflow(ReflowReason::LayoutChanged);
     }
 }
+
+/// Cut `s` to at most `max_width` columns, marking the cut with an ellipsis
+fn truncate_to_width(s: &str, max_width: usize) -> String {
+    let width: usize = s.chars().map(|c| c.width().unwrap_or(0)).sum();
+    if width <= max_width {
+        return s.to_string();
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    for c in s.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w > max_width.saturating_sub(1) {
+            break;
+        }
+        result.push(c);
+        used += w;
+    }
+    result.push('…');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::pty::{wait_until, MockPty};
+    use std::sync::Arc;
+
+    /// Tab whose initial pane runs on `mock`
+    fn mock_tab(mock: &MockPty) -> Tab {
+        let mut tab = Tab::new(1, "1:test".to_string(), 80, 24);
+        tab.focused_pane_mut()
+            .unwrap()
+            .session
+            .start_with_pty(Arc::new(mock.clone()));
+        tab
+    }
+
+    fn split_mock(tab: &mut Tab, direction: SplitDirection, mock: &MockPty) -> Option<PaneId> {
+        tab.split_with(direction, |session| {
+            session.start_with_pty(Arc::new(mock.clone()));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_split_with_mock() {
+        let first = MockPty::new();
+        let second = MockPty::new();
+        let mut tab = mock_tab(&first);
+
+        let new_id = split_mock(&mut tab, SplitDirection::Horizontal, &second).unwrap();
+        assert_eq!(tab.panes.len(), 2);
+        assert_eq!(tab.pane_order, vec![1, new_id]);
+        assert_eq!(tab.focused_pane, new_id);
+
+        // Both halves were resized to their inner size
+        let (w, h) = tab.panes[&new_id].inner_size();
+        assert_eq!(second.size(), (w, h));
+    }
+
+    #[test]
+    fn test_pane_spawn_spec() {
+        let tab = Tab::new(4, "4:test".to_string(), 80, 24);
+        let spec = tab.pane_spawn_spec(2, &SpawnSpec::default());
+        assert!(spec.env.contains(&("WTMUX_TAB".to_string(), "4".to_string())));
+        assert!(spec.env.contains(&("WTMUX_PANE".to_string(), "2".to_string())));
+    }
+
+    #[test]
+    fn test_split_start_failure() {
+        let mut tab = mock_tab(&MockPty::new());
+
+        let result = tab.split_with(SplitDirection::Vertical, |_| Err(PtyError::InvalidHandle));
+        assert!(result.is_none());
+        assert_eq!(tab.panes.len(), 1);
+    }
+
+    #[test]
+    fn test_cleanup_dead_panes() {
+        let first = MockPty::new();
+        let second = MockPty::new();
+        let mut tab = mock_tab(&first);
+        let second_id = split_mock(&mut tab, SplitDirection::Horizontal, &second).unwrap();
+
+        second.exit(0);
+        assert!(wait_until(|| !tab.panes[&second_id].session.is_running()));
+
+        tab.cleanup_dead_panes(false);
+        assert_eq!(tab.panes.len(), 1);
+        assert_eq!(tab.pane_order, vec![1]);
+        assert_eq!(tab.focused_pane, 1);
+        assert!(tab.is_running());
+    }
+
+    /// Text of the row the cursor is on
+    fn cursor_row_text(pane: &Pane) -> String {
+        let state = &pane.session.state;
+        let screen = state.active_screen();
+        let row = screen.visible_row_to_absolute(state.active_cursor().row);
+        (0..state.cols as usize)
+            .filter_map(|col| screen.get_char_at(row, col))
+            .collect::<String>()
+            .trim_end()
+            .to_string()
+    }
+
+    #[test]
+    fn test_remain_on_exit() {
+        let first = MockPty::new();
+        let second = MockPty::with_output(b"error: build failed");
+        let mut tab = mock_tab(&first);
+        let second_id = split_mock(&mut tab, SplitDirection::Horizontal, &second).unwrap();
+
+        second.exit(2);
+        assert!(wait_until(|| {
+            tab.process_output(usize::MAX);
+            !tab.panes[&second_id].session.is_running()
+        }));
+
+        tab.cleanup_dead_panes(true);
+        let pane = &tab.panes[&second_id];
+        assert!(pane.dead);
+        assert_eq!(cursor_row_text(pane), "[exited with code 2]");
+        assert_eq!(tab.pane_order, vec![1, second_id]);
+
+        // Still kept on the next cleanup, and closed like any other pane
+        tab.cleanup_dead_panes(true);
+        assert_eq!(tab.panes.len(), 2);
+        assert!(tab.close_pane());
+        assert_eq!(tab.pane_order, vec![1]);
+    }
+
+    #[test]
+    fn test_remain_on_exit_per_pane() {
+        let first = MockPty::new();
+        let second = MockPty::new();
+        let mut tab = mock_tab(&first);
+        let second_id = split_mock(&mut tab, SplitDirection::Horizontal, &second).unwrap();
+        tab.panes.get_mut(&second_id).unwrap().remain_on_exit = Some(false);
+
+        second.exit(0);
+        assert!(wait_until(|| !tab.panes[&second_id].session.is_running()));
+        tab.cleanup_dead_panes(true);
+        assert_eq!(tab.pane_order, vec![1]);
+    }
+
+    #[test]
+    fn test_respawn_dead_pane() {
+        let first = MockPty::new();
+        let second = MockPty::new();
+        let mut tab = mock_tab(&first);
+        let second_id = split_mock(&mut tab, SplitDirection::Horizontal, &second).unwrap();
+
+        second.exit(1);
+        assert!(wait_until(|| !tab.panes[&second_id].session.is_running()));
+        tab.cleanup_dead_panes(true);
+
+        let respawned = MockPty::new();
+        tab.respawn_pane_with(second_id, false, |session| {
+            session.start_with_pty(Arc::new(respawned.clone()));
+            Ok(())
+        })
+        .unwrap();
+
+        let pane = &tab.panes[&second_id];
+        assert!(!pane.dead);
+        assert!(pane.session.is_running());
+        assert!(pane.session.state.primary_screen.scrollback.is_empty());
+        assert_eq!(tab.pane_order, vec![1, second_id]);
+
+        // Mock-started panes have no spec to respawn from
+        assert!(tab.respawn_pane(second_id, None, false).is_err());
+    }
+
+    #[test]
+    fn test_respawn_keeps_scrollback() {
+        let first = MockPty::with_output(b"one\r\ntwo");
+        let mut tab = mock_tab(&first);
+        assert!(wait_until(|| {
+            tab.process_output(usize::MAX);
+            cursor_row_text(&tab.panes[&1]) == "two"
+        }));
+
+        let respawned = MockPty::new();
+        tab.respawn_pane_with(1, true, |session| {
+            session.start_with_pty(Arc::new(respawned.clone()));
+            Ok(())
+        })
+        .unwrap();
+
+        let pane = &tab.panes[&1];
+        let history: Vec<String> = pane.session.state.primary_screen.scrollback
+            .iter()
+            .map(|row| row.cells.iter().map(|cell| cell.c()).collect::<String>().trim_end().to_string())
+            .collect();
+        assert_eq!(history, vec!["one", "two"]);
+        assert_eq!(cursor_row_text(pane), "");
+        assert_eq!(pane.id, 1);
+    }
+
+    #[test]
+    fn test_remove_pane() {
+        let first = MockPty::new();
+        let second = MockPty::new();
+        let mut tab = mock_tab(&first);
+        let second_id = split_mock(&mut tab, SplitDirection::Vertical, &second).unwrap();
+
+        // Removing the focused pane focuses the other and gives it the space
+        assert!(tab.remove_pane(second_id));
+        assert!(!tab.remove_pane(second_id));
+        assert_eq!(tab.focused_pane, 1);
+        assert_eq!(tab.pane_order, vec![1]);
+        assert_eq!(tab.panes[&1].width, 80);
+
+        // The last pane can go too
+        assert!(tab.remove_pane(1));
+        assert!(tab.panes.is_empty());
+    }
+
+    #[test]
+    fn test_write_reaches_focused_pane_only() {
+        let first = MockPty::new();
+        let second = MockPty::new();
+        let mut tab = mock_tab(&first);
+        split_mock(&mut tab, SplitDirection::Vertical, &second).unwrap();
+
+        tab.focused_pane().unwrap().session.write(b"pwd\r").unwrap();
+        assert!(wait_until(|| second.written() == b"pwd\r"));
+        assert!(first.written().is_empty());
+    }
+
+    #[test]
+    fn test_automatic_rename() {
+        let mock = MockPty::with_output(b"\x1b]2;vim notes.txt\x07");
+        let mut tab = mock_tab(&mock);
+        assert!(wait_until(|| {
+            tab.process_output(usize::MAX);
+            tab.update_automatic_names()
+        }));
+        assert_eq!(tab.name, "1:vim notes.txt");
+        assert_eq!(tab.panes[&1].display_title(), "vim notes.txt");
+
+        // Live updates stop for the tab name once renamed by hand
+        tab.name = "editor".to_string();
+        tab.automatic_rename = false;
+        mock.push_output(b"\x1b]2;htop\x07");
+        assert!(wait_until(|| {
+            tab.process_output(usize::MAX);
+            tab.update_automatic_names()
+        }));
+        assert_eq!(tab.name, "editor");
+        assert_eq!(tab.panes[&1].display_title(), "htop");
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("bash", 24), "bash");
+        assert_eq!(truncate_to_width("abcdef", 4), "abc…");
+        assert_eq!(truncate_to_width("日本語の題名", 6), "日本…");
+    }
+}